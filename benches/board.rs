@@ -18,11 +18,18 @@ fn check_move_two(c: &mut Criterion) {
                 let mut game = board::Board::default();
                 game.add_random_tile();
                 game.add_random_tile();
-                let _ = board::check_move(game, twothousand_forty_eight::direction::Direction::UP);
-                let _ =
-                    board::check_move(game, twothousand_forty_eight::direction::Direction::RIGHT);
-                let _ =
-                    board::check_move(game, twothousand_forty_eight::direction::Direction::DOWN);
+                let _ = board::check_move(
+                    game.clone(),
+                    twothousand_forty_eight::direction::Direction::UP,
+                );
+                let _ = board::check_move(
+                    game.clone(),
+                    twothousand_forty_eight::direction::Direction::RIGHT,
+                );
+                let _ = board::check_move(
+                    game.clone(),
+                    twothousand_forty_eight::direction::Direction::DOWN,
+                );
                 let _ =
                     board::check_move(game, twothousand_forty_eight::direction::Direction::LEFT);
             })
@@ -41,11 +48,18 @@ fn check_move_four(c: &mut Criterion) {
                 game.add_random_tile();
                 game.add_random_tile();
                 game.add_random_tile();
-                let _ = board::check_move(game, twothousand_forty_eight::direction::Direction::UP);
-                let _ =
-                    board::check_move(game, twothousand_forty_eight::direction::Direction::RIGHT);
-                let _ =
-                    board::check_move(game, twothousand_forty_eight::direction::Direction::DOWN);
+                let _ = board::check_move(
+                    game.clone(),
+                    twothousand_forty_eight::direction::Direction::UP,
+                );
+                let _ = board::check_move(
+                    game.clone(),
+                    twothousand_forty_eight::direction::Direction::RIGHT,
+                );
+                let _ = board::check_move(
+                    game.clone(),
+                    twothousand_forty_eight::direction::Direction::DOWN,
+                );
                 let _ =
                     board::check_move(game, twothousand_forty_eight::direction::Direction::LEFT);
             })
@@ -53,5 +67,26 @@ fn check_move_four(c: &mut Criterion) {
     );
 }
 
-criterion_group!(benches, check_move_two, check_move_four);
+fn check_move_packed(c: &mut Criterion) {
+    bench_function(
+        c,
+        "check_move packed 4",
+        Box::new(|bencher| {
+            bencher.iter(|| {
+                let mut game = board::Board::default();
+                game.add_random_tile();
+                game.add_random_tile();
+                game.add_random_tile();
+                game.add_random_tile();
+                let packed = board::bitboard::BitBoard::try_from(&game).unwrap();
+                let _ = packed.check_move(twothousand_forty_eight::direction::Direction::UP);
+                let _ = packed.check_move(twothousand_forty_eight::direction::Direction::RIGHT);
+                let _ = packed.check_move(twothousand_forty_eight::direction::Direction::DOWN);
+                let _ = packed.check_move(twothousand_forty_eight::direction::Direction::LEFT);
+            })
+        }),
+    );
+}
+
+criterion_group!(benches, check_move_two, check_move_four, check_move_packed);
 criterion_main!(benches);
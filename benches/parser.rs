@@ -1,6 +1,6 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 
-use twothousand_forty_eight::v1::parser;
+use twothousand_forty_eight::{v1::parser, v2::recording::SeededRecording};
 
 pub const GAME3X3: &str = include_str!("../games/v1/GAME3X3A.txt");
 pub const GAME3X3B: &str = include_str!("../games/v1/GAME3X3B.txt");
@@ -8,6 +8,7 @@ pub const GAME4X4: &str = include_str!("../games/v1/GAME4X4.txt");
 pub const GAME4X4B: &str = include_str!("../games/v1/GAME4X4B.txt");
 pub const GAME4X4C: &str = include_str!("../games/v1/GAME4X4C.txt");
 pub const GAMEOBSCENE: &str = include_str!("../games/v1/GAME4X4BIG.txt");
+pub const GAME_V2: &str = include_str!("../games/v2/Ni4FIRM.txt");
 pub const GAMELIST: &str = include_str!("../games/v1/composite.txt");
 pub fn get_random_game() -> &'static str {
     let mut rng = rand::thread_rng();
@@ -81,6 +82,23 @@ fn parse_f(c: &mut Criterion) {
     );
 }
 
+fn parse_v2_text(c: &mut Criterion) {
+    bench_function_flat(
+        c,
+        "v2 text",
+        Box::new(|bencher| bencher.iter(|| GAME_V2.parse::<SeededRecording>().unwrap())),
+    );
+}
+
+fn parse_v2_binary(c: &mut Criterion) {
+    let bytes = GAME_V2.parse::<SeededRecording>().unwrap().to_bytes();
+    bench_function_flat(
+        c,
+        "v2 binary",
+        Box::new(move |bencher| bencher.iter(|| SeededRecording::from_bytes(&bytes).unwrap())),
+    );
+}
+
 criterion_group!(
     benches,
     parse_random,
@@ -89,6 +107,8 @@ criterion_group!(
     parse_c,
     parse_d,
     parse_e,
-    parse_f
+    parse_f,
+    parse_v2_text,
+    parse_v2_binary
 );
 criterion_main!(benches);
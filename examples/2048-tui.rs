@@ -12,64 +12,80 @@ use ratatui::{
 };
 use std::io::{self, Stdout};
 
-use twothousand_forty_eight::{unified::game::GameState, v2::recording::SeededRecording};
+use twothousand_forty_eight::{
+    unified::game::{Game, GameState},
+    v2::{annotation::Annotation, recording::SeededRecording},
+};
 struct State {
     gamestate: GameState,
-    history: SeededRecording,
+    game: Game,
     message: String,
     hiscore: usize,
 }
 impl State {
     pub fn new(message: Option<&str>) -> Self {
         let random_seed = rand::random();
-        let history = SeededRecording::empty(random_seed, 4, 4);
-        let gamestate = GameState::from_reconstructable_ruleset(&history).unwrap();
+        let game = Game::new(random_seed, 4, 4);
         Self {
-            gamestate,
-            history,
+            gamestate: game.state(),
+            game,
             message: message.unwrap_or_default().to_string(),
             hiscore: 0,
         }
     }
     pub fn save(&self) {
-        let history_string: String = (&self.history).into();
-        let stats = format!(
-            "------- STATS -------\nScore: {}\nRNG state: {}\nBreaks: {}\nMoves: {}\nAllowed moves: {:?}\nOver: {}\nWon: {}\n------- BOARD -------\n{}\n---------------------",
-            self.gamestate.score_max,
-            self.gamestate.board.rng_state,
-            self.gamestate.breaks,
-            self.history.moves.len(),
-            self.gamestate.allowed_moves,
-            self.gamestate.over,
-            self.gamestate.won,
-            self.gamestate.board,
-        );
-        std::fs::write("savegame.txt", format!("{history_string}\n{stats}")).unwrap();
+        let mut recording = self.game.recording().clone();
+        // The stats are written as comments, replacing the ones of the loaded game
+        recording
+            .annotations
+            .0
+            .retain(|annotation| !matches!(annotation, Annotation::Comment(_)));
+        for stat in [
+            format!("Score: {}", self.gamestate.score_max),
+            format!("RNG state: {}", self.gamestate.board.rng_state),
+            format!("Breaks: {}", self.gamestate.breaks),
+            format!("Moves: {}", recording.moves.len()),
+            format!("Allowed moves: {:?}", self.gamestate.allowed_moves),
+            format!("Over: {}", self.gamestate.over),
+            format!("Won: {}", self.gamestate.won),
+            format!("Board:\n{}", self.gamestate.board),
+        ] {
+            recording.annotations.push(Annotation::Comment(stat));
+        }
+        let history_string: String = (&recording).into();
+        std::fs::write("savegame.txt", history_string).unwrap();
     }
     pub fn load(path: &str) -> Self {
         let file_str = std::fs::read_to_string(path).unwrap();
-        // We only take the first line to allow comments & other cool stuff
-        let history_string = file_str.lines().next().unwrap();
-        let history: SeededRecording = match history_string.parse() {
+        // The lines after the recording are annotations
+        let history: SeededRecording = match file_str.parse() {
             Ok(history) => history,
             Err(e) => {
                 return Self::new(Some(&format!("Error parsing history: {:?}", e)));
             }
         };
-        let gamestate = match GameState::from_reconstructable_ruleset(&history) {
-            Ok(gamestate) => gamestate,
+        let game = match Game::from_recording(&history) {
+            Ok(game) => game,
             Err(e) => {
                 return Self::new(Some(&format!("Error reconstructing game: {:?}", e)));
             }
         };
+        let gamestate = game.state();
         let hiscore = gamestate.score_max;
         Self {
-            history,
+            game,
             message: format!("Loaded game from {path}"),
             gamestate,
             hiscore,
         }
     }
+    fn update(&mut self, message: &str) {
+        self.gamestate = self.game.state();
+        self.message = message.to_string();
+        if self.gamestate.score_max > self.hiscore {
+            self.hiscore = self.gamestate.score_max;
+        }
+    }
 }
 impl Default for State {
     fn default() -> Self {
@@ -159,6 +175,9 @@ fn run(terminal: &mut Terminal<CrosstermBackend<Stdout>>, mut gamestate: State)
                 KeyCode::Char('e') => {
                     revert_move(&mut gamestate);
                 }
+                KeyCode::Char('y') => {
+                    redo_move(&mut gamestate);
+                }
                 KeyCode::Char('r') => {
                     let hiscore = gamestate.hiscore;
                     gamestate = State::new(None);
@@ -183,57 +202,26 @@ fn run(terminal: &mut Terminal<CrosstermBackend<Stdout>>, mut gamestate: State)
 }
 
 fn move_in_direction(state: &mut State, direction: twothousand_forty_eight::direction::Direction) {
-    if !state.gamestate.allowed_moves.contains(&direction) {
-        state.message = format!("Move to direction {:?} not allowed.", direction);
-        //return;
-    }
-    let mut new_history = state.history.clone();
-    new_history.moves.push(direction);
-    let history_string: String = (&new_history).into();
-    match history_string.parse::<SeededRecording>() {
-        Ok(history) => match GameState::from_reconstructable_ruleset(&history) {
-            Ok(gamestate) => {
-                state.gamestate = gamestate;
-                state.message = String::new();
-                state.history = history;
-                if state.gamestate.score_max > state.hiscore {
-                    state.hiscore = state.gamestate.score_max;
-                }
-            }
-            Err(e) => {
-                state.message = format!("{:?}", e);
-            }
-        },
+    match state.game.play(direction) {
+        Ok(()) => state.update(""),
         Err(e) => {
             state.message = format!("{:?}", e);
         }
     }
 }
 fn revert_move(state: &mut State) {
-    if state.history.moves.is_empty() {
-        state.message = "No moves to revert.".to_string();
-        return;
+    match state.game.undo() {
+        Some(_) => state.update("Reverted move."),
+        None => {
+            state.message = "No moves to revert.".to_string();
+        }
     }
-    let mut new_history = state.history.clone();
-    new_history.moves.pop();
-    let history_string: String = (&new_history).into();
-    match history_string.parse::<SeededRecording>() {
-        Ok(history) => match GameState::from_reconstructable_ruleset(&history) {
-            Ok(gamestate) => {
-                state.gamestate = gamestate;
-                state.message = String::new();
-                state.history = history;
-                if state.gamestate.score_max > state.hiscore {
-                    state.hiscore = state.gamestate.score_max;
-                }
-                state.message = String::from("Reverted move.");
-            }
-            Err(e) => {
-                state.message = format!("{:?}", e);
-            }
-        },
-        Err(e) => {
-            state.message = format!("{:?}", e);
+}
+fn redo_move(state: &mut State) {
+    match state.game.redo() {
+        Some(_) => state.update("Redid move."),
+        None => {
+            state.message = "No moves to redo.".to_string();
         }
     }
 }
@@ -286,7 +274,7 @@ fn render_app(frame: &mut ratatui::Frame<CrosstermBackend<Stdout>>, state: &Stat
     frame.render_widget(hiscore, chunks[2]);
     let seed = Paragraph::new(format!("Seed/State: {}", gamestate.board.rng_state,));
     frame.render_widget(seed, chunks[3]);
-    let message = Paragraph::new(format!("{}", state.message));
+    let message = Paragraph::new(state.message.clone());
     frame.render_widget(message, chunks[4]);
     let board = Table::new(gamestate.board.tiles.iter().map(|row| {
         Row::new(
@@ -1,14 +1,21 @@
-use twothousand_forty_eight::random::{lcg_sane, Pickable};
+use twothousand_forty_eight::random::{lcg_sane, Pickable, RandAlgo};
 
 fn main() {
     const OPTIONS: [usize; 10] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
-    let mut seed = 0;
-    let mut count = [0; OPTIONS.len()];
-    for _i in 0..1000000 {
-        let n = OPTIONS.pick_lcg(&mut seed);
-        count[*n] += 1;
+    for algo in [
+        RandAlgo::LCG,
+        RandAlgo::Xorshift32,
+        RandAlgo::Pcg32,
+        RandAlgo::SplitMix32,
+    ] {
+        let mut seed = 0;
+        let mut count = [0; OPTIONS.len()];
+        for _i in 0..1000000 {
+            let n = OPTIONS.pick(&algo, &mut seed);
+            count[*n] += 1;
+        }
+        println!("{:?}: {:?}", algo, count);
     }
-    println!("{:?}", count);
 
     // 2d noise
     let mut seed = 0;
@@ -0,0 +1,320 @@
+//! Looks for signs of a game being played by a bot.
+//!
+//! A valid game can still be played by a program, so the [Analyzer] goes through the moves of a game and
+//! computes [Features] that tend to differ between humans and bots. These are combined into a suspicion score
+//! along with a human readable explanation of what stood out, meant for triaging games rather than rejecting them outright.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    heuristic::{Heuristic, WeightedSum},
+    Expectimax,
+};
+use crate::{
+    board::{check_move, Board},
+    direction::{Direction, MOVE_DIRECTIONS},
+    unified::reconstruction::HistoryReconstruction,
+};
+
+/// Games with fewer analysed moves than this don't get a suspicion score
+pub const MIN_ANALYSED_MOVES: usize = 30;
+
+/// How often humans tend to agree with the search at most
+const HUMAN_AGREEMENT: f64 = 0.6;
+/// Entropy in bits below which the moves are suspiciously repetitive
+const LOW_ENTROPY: f64 = 1.2;
+/// How often humans tend to keep their biggest tile in the same corner at most
+const HUMAN_CORNER_CONSISTENCY: f64 = 0.9;
+
+const AGREEMENT_WEIGHT: f64 = 0.6;
+const ENTROPY_WEIGHT: f64 = 0.15;
+const CORNER_WEIGHT: f64 = 0.15;
+const BREAK_WEIGHT: f64 = 0.1;
+
+/// Measurements of how a game was played
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Features {
+    /// Moves in one of the [MOVE_DIRECTIONS], breaks excluded
+    pub analysed_moves: usize,
+    /// How often the move played was the best move according to the search
+    pub agreement: f64,
+    /// Shannon entropy of the directions played, in bits. Playing every direction equally often gives 2.
+    pub entropy: f64,
+    /// How many times each direction in [MOVE_DIRECTIONS] was played
+    pub direction_counts: [usize; 4],
+    /// The largest fraction of positions that had the biggest tile in the same corner
+    pub corner_consistency: f64,
+    /// When breaks were used, as indices into the moves
+    pub break_positions: Vec<usize>,
+    /// How many of the breaks were used with at most one valid move or one empty cell left, i.e. about as late as possible
+    pub late_breaks: usize,
+}
+
+/// The outcome of [Analyzer::analyze]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Analysis {
+    pub features: Features,
+    /// From 0 for nothing unusual to 1 for a game that looks like it was played by a bot
+    pub suspicion: f64,
+    /// What contributed to the suspicion
+    pub explanations: Vec<String>,
+}
+
+/// Computes [Features] of games and scores them
+pub struct Analyzer<H: Heuristic = WeightedSum> {
+    /// The search the moves are compared against
+    pub expectimax: Expectimax<H>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::new(Expectimax::default())
+    }
+}
+
+impl<H: Heuristic> Analyzer<H> {
+    pub fn new(expectimax: Expectimax<H>) -> Self {
+        Self { expectimax }
+    }
+
+    /// Analyse a game from its moves and their [reconstruction](crate::unified::reconstruction::Reconstructable), where
+    /// the move at each index leads from the board at the same index of the history to the next one.
+    ///
+    /// Only positions that fit in a [BitBoard](crate::board::bitboard::BitBoard) are compared against the search,
+    /// larger boards only get the other features.
+    pub fn analyze(&self, moves: &[Direction], reconstruction: &HistoryReconstruction) -> Analysis {
+        let history = &reconstruction.history;
+        let break_positions: Vec<usize> = reconstruction
+            .validation_data
+            .break_positions
+            .iter()
+            .flatten()
+            .copied()
+            .collect();
+
+        let mut direction_counts = [0; 4];
+        let mut analysed_moves = 0;
+        let (mut searched, mut agreed) = (0, 0);
+        for (&direction, board) in moves.iter().zip(history) {
+            let Some(index) = MOVE_DIRECTIONS.iter().position(|&d| d == direction) else {
+                continue;
+            };
+            analysed_moves += 1;
+            direction_counts[index] += 1;
+            if let Ok(hint) = self.expectimax.hint(board) {
+                searched += 1;
+                if hint.best == Some(direction) {
+                    agreed += 1;
+                }
+            }
+        }
+
+        let features = Features {
+            analysed_moves,
+            agreement: ratio(agreed, searched),
+            entropy: entropy(&direction_counts),
+            direction_counts,
+            corner_consistency: corner_consistency(history),
+            late_breaks: break_positions
+                .iter()
+                .filter(|&&index| history.get(index).is_some_and(is_cornered))
+                .count(),
+            break_positions,
+        };
+        score(features)
+    }
+}
+
+/// Combine the features into a suspicion score
+fn score(features: Features) -> Analysis {
+    let mut suspicion = 0.0;
+    let mut explanations = vec![];
+    if features.analysed_moves < MIN_ANALYSED_MOVES {
+        explanations.push(format!(
+            "only {} moves could be analysed, at least {} are needed",
+            features.analysed_moves, MIN_ANALYSED_MOVES
+        ));
+        return Analysis {
+            features,
+            suspicion,
+            explanations,
+        };
+    }
+
+    if features.agreement > HUMAN_AGREEMENT {
+        suspicion +=
+            AGREEMENT_WEIGHT * (features.agreement - HUMAN_AGREEMENT) / (1.0 - HUMAN_AGREEMENT);
+        explanations.push(format!(
+            "{:.0}% of the moves match the best move of the search",
+            features.agreement * 100.0
+        ));
+    }
+    if features.entropy < LOW_ENTROPY {
+        suspicion += ENTROPY_WEIGHT * (LOW_ENTROPY - features.entropy) / LOW_ENTROPY;
+        explanations.push(format!(
+            "the moves are repetitive, {:.2} bits of entropy",
+            features.entropy
+        ));
+    }
+    if features.corner_consistency > HUMAN_CORNER_CONSISTENCY {
+        suspicion += CORNER_WEIGHT * (features.corner_consistency - HUMAN_CORNER_CONSISTENCY)
+            / (1.0 - HUMAN_CORNER_CONSISTENCY);
+        explanations.push(format!(
+            "the biggest tile stayed in the same corner {:.0}% of the time",
+            features.corner_consistency * 100.0
+        ));
+    }
+    if features.late_breaks > 1 && features.late_breaks == features.break_positions.len() {
+        suspicion += BREAK_WEIGHT;
+        explanations.push(format!(
+            "all {} breaks were used with at most one move or empty cell left",
+            features.late_breaks
+        ));
+    }
+    Analysis {
+        features,
+        suspicion: suspicion.clamp(0.0, 1.0),
+        explanations,
+    }
+}
+
+/// True if the board has at most one valid move or at most one empty cell left
+fn is_cornered(board: &Board) -> bool {
+    let valid_moves = MOVE_DIRECTIONS
+        .into_iter()
+        .filter(|&direction| check_move(board.clone(), direction).is_ok())
+        .count();
+    valid_moves <= 1 || board.get_non_occupied_tiles().len() <= 1
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        _ => count as f64 / total as f64,
+    }
+}
+
+fn entropy(counts: &[usize]) -> f64 {
+    let total: usize = counts.iter().sum();
+    -counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = ratio(count, total);
+            p * p.log2()
+        })
+        .sum::<f64>()
+}
+
+/// The largest fraction of boards that had their biggest tile in a given corner
+fn corner_consistency(history: &[Board]) -> f64 {
+    let mut corners = [0; 4];
+    let mut boards = 0;
+    for board in history {
+        let Some(max) = board.get_occupied_tiles().iter().map(|t| t.value).max() else {
+            continue;
+        };
+        boards += 1;
+        let (right, bottom) = (
+            board.width.saturating_sub(1),
+            board.height.saturating_sub(1),
+        );
+        for (corner, (x, y)) in [(0, 0), (right, 0), (0, bottom), (right, bottom)]
+            .into_iter()
+            .enumerate()
+        {
+            if board.tiles[y][x].is_some_and(|tile| tile.value == max) {
+                corners[corner] += 1;
+            }
+        }
+    }
+    ratio(corners.into_iter().max().unwrap_or(0), boards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        unified::{game::Game, reconstruction::Reconstructable},
+        v2::{
+            recording::SeededRecording,
+            test_data::{GAME_NI4FIRM, GAME_WON_3_BREAKS},
+        },
+    };
+
+    fn analyzer() -> Analyzer {
+        Analyzer::new(Expectimax::new(1, WeightedSum::default()))
+    }
+
+    #[test]
+    fn bot_is_suspicious() {
+        let analyzer = analyzer();
+        let mut game = Game::new(1234, 4, 4);
+        for _ in 0..60 {
+            let best = analyzer
+                .expectimax
+                .hint(game.board())
+                .unwrap()
+                .best
+                .unwrap();
+            game.play(best).unwrap();
+        }
+        let recording = game.recording();
+        let bot = analyzer.analyze(&recording.moves, &recording.reconstruct().unwrap());
+        assert_eq!(bot.features.analysed_moves, 60);
+        assert_eq!(bot.features.agreement, 1.0);
+        assert!(bot.suspicion >= AGREEMENT_WEIGHT);
+        assert!(!bot.explanations.is_empty());
+
+        let recording: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        let human = analyzer.analyze(&recording.moves, &recording.reconstruct().unwrap());
+        assert!(human.features.analysed_moves >= MIN_ANALYSED_MOVES);
+        assert!(human.suspicion < bot.suspicion);
+    }
+
+    #[test]
+    fn too_short() {
+        let mut game = Game::new(1234, 4, 4);
+        game.play(Direction::UP).unwrap();
+        let recording = game.recording();
+        let analysis = analyzer().analyze(&recording.moves, &recording.reconstruct().unwrap());
+        assert_eq!(analysis.suspicion, 0.0);
+        assert_eq!(analysis.explanations.len(), 1);
+    }
+
+    #[test]
+    fn late_breaks() {
+        let recording: SeededRecording = GAME_WON_3_BREAKS.parse().unwrap();
+        let reconstruction = recording.reconstruct().unwrap();
+        let analysis = analyzer().analyze(&recording.moves, &reconstruction);
+        assert_eq!(analysis.features.break_positions.len(), 3);
+        assert_eq!(analysis.features.late_breaks, 2);
+
+        let mut board = Board::new(3, 3, 0);
+        board.set_tile(1, 1, 2);
+        assert!(!is_cornered(&board));
+        // Only down is left
+        let mut board = Board::new(3, 3, 0);
+        for (x, value) in [2, 4, 8].into_iter().enumerate() {
+            board.set_tile(x, 0, value);
+        }
+        assert!(is_cornered(&board));
+        // Only one empty cell is left
+        let mut board = Board::new(3, 3, 0);
+        for (index, value) in [2, 2, 4, 4, 8, 8, 16, 16].into_iter().enumerate() {
+            board.set_tile(index % 3, index / 3, value);
+        }
+        assert!(is_cornered(&board));
+    }
+
+    #[test]
+    fn entropy_bits() {
+        assert_eq!(entropy(&[5, 5, 5, 5]), 2.0);
+        assert_eq!(entropy(&[10, 0, 0, 0]), 0.0);
+        assert_eq!(entropy(&[0, 0, 0, 0]), 0.0);
+    }
+}
@@ -0,0 +1,162 @@
+//! Heuristics used to score the leaves of the [Expectimax](super::Expectimax) search.
+//!
+//! All of the provided heuristics work on tile exponents rather than values, so that a single big tile doesn't drown out everything else.
+
+use crate::board::bitboard::BitBoard;
+
+/// Scores a board, higher is better
+pub trait Heuristic {
+    fn evaluate(&self, board: &BitBoard) -> f64;
+}
+
+/// Rewards empty cells
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmptyCells;
+
+impl Heuristic for EmptyCells {
+    fn evaluate(&self, board: &BitBoard) -> f64 {
+        board.get_empty_positions().len() as f64
+    }
+}
+
+/// Penalizes rows and columns that aren't sorted in either direction
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Monotonicity;
+
+impl Heuristic for Monotonicity {
+    fn evaluate(&self, board: &BitBoard) -> f64 {
+        let mut penalty = 0.0;
+        for line in lines(board) {
+            let (mut increasing, mut decreasing) = (0.0, 0.0);
+            for pair in line.windows(2) {
+                let (a, b) = (pair[0] as f64, pair[1] as f64);
+                if a < b {
+                    increasing += b - a;
+                } else {
+                    decreasing += a - b;
+                }
+            }
+            penalty += f64::min(increasing, decreasing);
+        }
+        -penalty
+    }
+}
+
+/// Penalizes differences between neighbouring tiles
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Smoothness;
+
+impl Heuristic for Smoothness {
+    fn evaluate(&self, board: &BitBoard) -> f64 {
+        let mut penalty = 0.0;
+        for line in lines(board) {
+            let tiles: Vec<u32> = line.into_iter().filter(|e| *e != 0).collect();
+            for pair in tiles.windows(2) {
+                penalty += (pair[0] as f64 - pair[1] as f64).abs();
+            }
+        }
+        -penalty
+    }
+}
+
+/// Rewards keeping the biggest tiles close to one of the corners
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CornerWeighting;
+
+impl Heuristic for CornerWeighting {
+    fn evaluate(&self, board: &BitBoard) -> f64 {
+        let (width, height) = (board.width, board.height);
+        let max_distance = (width + height).saturating_sub(2);
+        let corners = [
+            (0, 0),
+            (width.saturating_sub(1), 0),
+            (0, height.saturating_sub(1)),
+            (width.saturating_sub(1), height.saturating_sub(1)),
+        ];
+        corners
+            .iter()
+            .map(|(cx, cy)| {
+                let mut score = 0.0;
+                for y in 0..height {
+                    for x in 0..width {
+                        let distance = x.abs_diff(*cx) + y.abs_diff(*cy);
+                        let weight = (max_distance - distance) as f64;
+                        score += weight * board.get_exponent(x, y) as f64;
+                    }
+                }
+                score
+            })
+            .fold(f64::MIN, f64::max)
+            / max_distance.max(1) as f64
+    }
+}
+
+/// Combines multiple heuristics into one with a weight for each
+pub struct WeightedSum(pub Vec<(f64, Box<dyn Heuristic + Send + Sync>)>);
+
+impl Heuristic for WeightedSum {
+    fn evaluate(&self, board: &BitBoard) -> f64 {
+        self.0
+            .iter()
+            .map(|(weight, heuristic)| weight * heuristic.evaluate(board))
+            .sum()
+    }
+}
+
+/// A reasonable mix of all the provided heuristics
+impl Default for WeightedSum {
+    fn default() -> Self {
+        WeightedSum(vec![
+            (2.7, Box::new(EmptyCells)),
+            (1.0, Box::new(Monotonicity)),
+            (0.1, Box::new(Smoothness)),
+            (0.5, Box::new(CornerWeighting)),
+        ])
+    }
+}
+
+/// All the rows and columns of the board as exponents
+fn lines(board: &BitBoard) -> Vec<Vec<u32>> {
+    let rows =
+        (0..board.height).map(|y| (0..board.width).map(|x| board.get_exponent(x, y)).collect());
+    let columns = (0..board.width).map(|x| {
+        (0..board.height)
+            .map(|y| board.get_exponent(x, y))
+            .collect()
+    });
+    rows.chain(columns).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(values: &[[usize; 4]; 4]) -> BitBoard {
+        let mut board = BitBoard::new(4, 4, 0).unwrap();
+        for (y, row) in values.iter().enumerate() {
+            for (x, value) in row.iter().enumerate() {
+                board.set_value(x, y, *value).unwrap();
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn sorted_is_better() {
+        let sorted = board(&[[16, 8, 4, 2], [8, 4, 2, 0], [4, 2, 0, 0], [2, 0, 0, 0]]);
+        let scattered = board(&[[2, 8, 2, 16], [0, 4, 0, 4], [8, 0, 2, 0], [2, 0, 4, 2]]);
+        for heuristic in [
+            &Monotonicity as &dyn Heuristic,
+            &Smoothness,
+            &CornerWeighting,
+        ] {
+            assert!(heuristic.evaluate(&sorted) > heuristic.evaluate(&scattered));
+        }
+    }
+
+    #[test]
+    fn empty_cells() {
+        let board = board(&[[2, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 4]]);
+        assert_eq!(EmptyCells.evaluate(&board), 14.0);
+    }
+}
@@ -0,0 +1,213 @@
+//! A depth-limited expectimax player, e.g. for giving hints.
+//!
+//! The search alternates between the player picking the best move and the game adding a random tile,
+//! weighted by how likely each tile is according to the spawn weights. The leaves are scored with a [Heuristic].
+
+pub mod analysis;
+pub mod heuristic;
+pub mod oracle;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{
+        bitboard::{BitBoard, BitBoardError},
+        tile::{SpawnWeight, CLASSIC_SPAWN_WEIGHTS},
+        Board,
+    },
+    direction::{Direction, MOVE_DIRECTIONS},
+    rules::Ruleset,
+};
+
+use self::heuristic::{Heuristic, WeightedSum};
+
+/// Added to the heuristic of boards that have no moves left
+const GAME_OVER_PENALTY: f64 = 1_000_000.0;
+
+/// The expected value of a single move
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct MoveEvaluation {
+    pub direction: Direction,
+    /// The expected value of the move, None if the move isn't possible
+    pub value: Option<f64>,
+}
+
+/// The result of a search
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Hint {
+    /// The move with the highest expected value, None if no moves are possible
+    pub best: Option<Direction>,
+    /// The evaluation of every move in [MOVE_DIRECTIONS]
+    pub evaluations: Vec<MoveEvaluation>,
+}
+
+/// Searches for the best move with expectimax
+pub struct Expectimax<H: Heuristic = WeightedSum> {
+    /// How many moves to look ahead, 1 only looks at the immediate result of each move
+    pub depth: usize,
+    pub heuristic: H,
+    /// The values of the tiles the game adds, see [Ruleset::spawn_weights](crate::rules::Ruleset::spawn_weights).
+    /// The search assumes a single tile is added per move.
+    pub spawn_weights: Vec<SpawnWeight>,
+}
+
+impl Default for Expectimax {
+    fn default() -> Self {
+        Self::new(2, WeightedSum::default())
+    }
+}
+
+impl<H: Heuristic> Expectimax<H> {
+    pub fn new(depth: usize, heuristic: H) -> Self {
+        Self {
+            depth,
+            heuristic,
+            spawn_weights: CLASSIC_SPAWN_WEIGHTS.to_vec(),
+        }
+    }
+
+    /// Search with the spawn weights of `rules` instead of the classic ones
+    pub fn with_rules(mut self, rules: &dyn Ruleset, board: &Board) -> Self {
+        self.spawn_weights = rules.spawn_weights(board).to_vec();
+        self
+    }
+
+    /// Evaluate every move on the board and pick the best one
+    pub fn hint(&self, board: &Board) -> Result<Hint, BitBoardError> {
+        Ok(self.hint_packed(&BitBoard::try_from(board)?))
+    }
+
+    /// Same as [Expectimax::hint] for a board that is already packed
+    pub fn hint_packed(&self, board: &BitBoard) -> Hint {
+        let evaluations: Vec<MoveEvaluation> = MOVE_DIRECTIONS
+            .into_iter()
+            .map(|direction| MoveEvaluation {
+                direction,
+                value: board
+                    .check_move(direction)
+                    .ok()
+                    .map(|(moved, _)| self.chance(&moved, self.depth.max(1) - 1)),
+            })
+            .collect();
+        let best = evaluations
+            .iter()
+            .filter_map(|e| Some((e.direction, e.value?)))
+            .fold(
+                None,
+                |best: Option<(Direction, f64)>, (direction, value)| match best {
+                    Some((_, best_value)) if best_value >= value => best,
+                    _ => Some((direction, value)),
+                },
+            )
+            .map(|(direction, _)| direction);
+        Hint { best, evaluations }
+    }
+
+    /// The value of the best move on the board
+    fn max(&self, board: &BitBoard, depth: usize) -> f64 {
+        MOVE_DIRECTIONS
+            .into_iter()
+            .filter_map(|direction| board.check_move(direction).ok())
+            .map(|(moved, _)| self.chance(&moved, depth - 1))
+            .reduce(f64::max)
+            .unwrap_or_else(|| self.heuristic.evaluate(board) - GAME_OVER_PENALTY)
+    }
+
+    /// The expected value of the board after a random tile has been added
+    fn chance(&self, board: &BitBoard, depth: usize) -> f64 {
+        let empty = board.get_empty_positions();
+        if depth == 0 || empty.is_empty() {
+            return self.heuristic.evaluate(board);
+        }
+        // Summed as u64 so that large weights can't overflow
+        let total_weight: u64 = self.spawn_weights.iter().map(|w| u64::from(w.weight)).sum();
+        let mut total = 0.0;
+        for (x, y) in &empty {
+            for spawn in self.spawn_weights.iter().filter(|w| w.weight > 0) {
+                let mut spawned = *board;
+                // Tiles that can't be packed are left out of the expected value
+                if spawned.set_value(*x, *y, spawn.value).is_ok() {
+                    total += spawn.weight as f64 * self.max(&spawned, depth);
+                }
+            }
+        }
+        total / (empty.len() as f64 * total_weight.max(1) as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::validator::initialize_board;
+
+    #[test]
+    fn evaluates_every_move() {
+        let mut board = Board::new(4, 4, 0);
+        board.set_tile(0, 0, 2);
+        board.set_tile(0, 1, 4);
+        let hint = Expectimax::default().hint(&board).unwrap();
+        assert_eq!(hint.evaluations.len(), 4);
+        for evaluation in &hint.evaluations {
+            match evaluation.direction {
+                Direction::UP | Direction::LEFT => assert_eq!(evaluation.value, None),
+                _ => assert!(evaluation.value.is_some()),
+            }
+        }
+        assert!(matches!(
+            hint.best,
+            Some(Direction::RIGHT | Direction::DOWN)
+        ));
+    }
+
+    #[test]
+    fn no_moves() {
+        let mut board = Board::new(2, 2, 0);
+        board.set_tile(0, 0, 2);
+        board.set_tile(1, 0, 4);
+        board.set_tile(0, 1, 4);
+        board.set_tile(1, 1, 2);
+        let hint = Expectimax::default().hint(&board).unwrap();
+        assert_eq!(hint.best, None);
+        assert!(hint.evaluations.iter().all(|e| e.value.is_none()));
+    }
+
+    #[test]
+    fn large_spawn_weights() {
+        let ai = Expectimax {
+            spawn_weights: vec![
+                SpawnWeight {
+                    value: 2,
+                    weight: u32::MAX,
+                },
+                SpawnWeight {
+                    value: 4,
+                    weight: u32::MAX,
+                },
+            ],
+            ..Default::default()
+        };
+        let mut board = Board::new(4, 4, 0);
+        board.set_tile(0, 0, 2);
+        assert!(ai.hint(&board).unwrap().best.is_some());
+    }
+
+    #[test]
+    fn plays_well() {
+        let ai = Expectimax::default();
+        let mut board = initialize_board(4, 4, 1234, 2);
+        let max_tile = |board: &Board| board.get_all_tiles().iter().map(|t| t.value).max();
+        while max_tile(&board) < Some(512) {
+            let best = ai
+                .hint(&board)
+                .unwrap()
+                .best
+                .expect("the game shouldn't be over yet");
+            board.move_in_direction(best).unwrap();
+            board.add_random_tile();
+        }
+    }
+}
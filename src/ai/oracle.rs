@@ -0,0 +1,273 @@
+//! A search that knows the seed of the game.
+//!
+//! V2 games are fully determined by their seed, so unlike [Expectimax](super::Expectimax) the [Oracle] doesn't have to
+//! plan for every tile that might be added: it knows exactly which tile will be added after each move.
+//! This is useful for designing puzzles, and for checking whether a game is suspiciously close to what an oracle would play,
+//! see [Oracle::luck_ratio].
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::heuristic::{Heuristic, WeightedSum};
+use crate::{
+    board::{
+        bitboard::{BitBoard, MAX_EXACT_CELLS},
+        Board,
+    },
+    direction::{Direction, MOVE_DIRECTIONS},
+    rules::RulesetProvider,
+    unified::validation::Validatable,
+    v2::{
+        recording::SeededRecording,
+        replay::{replay_moves_packed, MoveReplayError},
+    },
+};
+
+/// What the [Oracle] is searching for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Goal {
+    /// A tile with at least this value on the board
+    Tile(usize),
+    /// At least this score
+    Score(usize),
+    /// Survive this many moves past the end of the prefix
+    Moves(usize),
+}
+
+#[derive(Error, Debug, Clone, Serialize, Deserialize)]
+pub enum OracleError {
+    #[error("the prefix isn't valid: {0}")]
+    InvalidPrefix(#[from] MoveReplayError),
+
+    #[error("a {0}x{1} board can't be searched, at most {MAX_EXACT_CELLS} cells are supported")]
+    Unsupported(usize, usize),
+}
+
+/// A beam search over the exact future of a seeded game.
+///
+/// Every step expands the moves of each candidate position, adds the tile the seed dictates and keeps the
+/// `beam_width` best positions according to the heuristic. Breaks are never used.
+pub struct Oracle<H: Heuristic = WeightedSum> {
+    /// How many positions are kept after each move
+    pub beam_width: usize,
+    /// How many moves past the prefix are searched before giving up
+    pub max_moves: usize,
+    pub heuristic: H,
+}
+
+impl Default for Oracle {
+    fn default() -> Self {
+        Self {
+            beam_width: 64,
+            max_moves: 10_000,
+            heuristic: WeightedSum::default(),
+        }
+    }
+}
+
+/// A position in the search, linked to the position it was reached from
+struct Node {
+    board: BitBoard,
+    score: usize,
+    /// Index of the parent and the move that led here, None for the end of the prefix
+    parent: Option<(usize, Direction)>,
+}
+
+impl<H: Heuristic> Oracle<H> {
+    pub fn new(beam_width: usize, max_moves: usize, heuristic: H) -> Self {
+        Self {
+            beam_width,
+            max_moves,
+            heuristic,
+        }
+    }
+
+    /// Search for moves that continue `prefix` until `goal` is reached.
+    ///
+    /// Returns the prefix extended with the moves that were found, or None if none of the searched lines reached the goal.
+    pub fn search(
+        &self,
+        prefix: &SeededRecording,
+        goal: Goal,
+    ) -> Result<Option<SeededRecording>, OracleError> {
+        let reconstruction = replay_moves_packed(prefix)?
+            .ok_or(OracleError::Unsupported(prefix.width, prefix.height))?;
+        // There's always at least the initial board
+        let start = *reconstruction.history.last().unwrap();
+        let rules = prefix.rules();
+        let unpacked = Board::from(&start);
+        let spawn_weights = rules.spawn_weights(&unpacked);
+        let spawns_per_move = rules.spawns_per_move(&unpacked);
+        let mut nodes = vec![Node {
+            board: start,
+            score: reconstruction.validation_data.score_end,
+            parent: None,
+        }];
+        let mut beam = vec![0];
+        let mut seen = HashSet::new();
+
+        for moves in 0..=self.max_moves {
+            if let Some(index) = beam
+                .iter()
+                .copied()
+                .find(|i| reached(&nodes[*i], goal, moves))
+            {
+                return Ok(Some(extend(prefix, &nodes, index)));
+            }
+            if moves == self.max_moves {
+                break;
+            }
+
+            let mut candidates = vec![];
+            for index in beam {
+                for direction in MOVE_DIRECTIONS {
+                    // Overflowing the packed board ends the line just like an invalid move
+                    let Ok((mut board, score_gain)) = nodes[index].board.check_move(direction)
+                    else {
+                        continue;
+                    };
+                    let spawned = (0..spawns_per_move)
+                        .all(|_| board.add_random_tile_with(spawn_weights).is_ok());
+                    if !spawned || !seen.insert(board) {
+                        continue;
+                    }
+                    candidates.push((self.heuristic.evaluate(&board), nodes.len()));
+                    nodes.push(Node {
+                        board,
+                        score: nodes[index].score + score_gain,
+                        parent: Some((index, direction)),
+                    });
+                }
+            }
+            if candidates.is_empty() {
+                break;
+            }
+            candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+            beam = candidates
+                .into_iter()
+                .take(self.beam_width.max(1))
+                .map(|(_, index)| index)
+                .collect();
+        }
+        Ok(None)
+    }
+
+    /// Compare a finished game to the oracle's line over the same amount of moves, starting from the same seed.
+    ///
+    /// Returns the final score of the recording divided by the score of the oracle. Human players usually stay below 1,
+    /// a game close to or above it looks like it was planned with knowledge of the seed.
+    /// None if the oracle doesn't last as many moves as the recording.
+    pub fn luck_ratio(&self, recording: &SeededRecording) -> Result<Option<f64>, OracleError> {
+        let recorded = recording.validate()?.score_end;
+        let start = SeededRecording {
+            moves: vec![],
+            timings: None,
+            ..recording.clone()
+        };
+        let Some(line) = self.search(&start, Goal::Moves(recording.moves.len()))? else {
+            return Ok(None);
+        };
+        let best = line.validate()?.score_end;
+        Ok(Some(recorded as f64 / best.max(1) as f64))
+    }
+}
+
+/// Append the moves leading to `nodes[index]` to the prefix
+fn extend(prefix: &SeededRecording, nodes: &[Node], mut index: usize) -> SeededRecording {
+    let mut moves = vec![];
+    while let Some((parent, direction)) = nodes[index].parent {
+        moves.push(direction);
+        index = parent;
+    }
+    let mut recording = prefix.clone();
+    // The found moves weren't played by anyone, so there's no timing data for them
+    recording.timings = None;
+    recording.moves.extend(moves.into_iter().rev());
+    recording
+}
+
+fn reached(node: &Node, goal: Goal, moves: usize) -> bool {
+    match goal {
+        Goal::Tile(value) => node.board.get_max_value() >= value,
+        Goal::Score(score) => node.score >= score,
+        Goal::Moves(target) => moves >= target,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{unified::reconstruction::Reconstructable, v2::test_data::GAME_NI4FIRM};
+
+    #[test]
+    fn reaches_tile() {
+        let prefix = SeededRecording::empty(1234, 4, 4);
+        let found = Oracle::default()
+            .search(&prefix, Goal::Tile(256))
+            .unwrap()
+            .unwrap();
+        let reconstruction = found.reconstruct().unwrap();
+        let last = reconstruction.history.last().unwrap();
+        assert!(last.get_all_tiles().iter().any(|t| t.value >= 256));
+        // The goal is checked after every move, so the last move is the one that reached it
+        let before = &reconstruction.history[reconstruction.history.len() - 2];
+        assert!(before.get_all_tiles().iter().all(|t| t.value < 256));
+    }
+
+    #[test]
+    fn continues_prefix() {
+        let mut prefix: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        // The full game is lost already
+        prefix.moves.truncate(prefix.moves.len() / 2);
+        let found = Oracle::default()
+            .search(&prefix, Goal::Moves(20))
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.moves.len(), prefix.moves.len() + 20);
+        assert_eq!(found.moves[..prefix.moves.len()], prefix.moves[..]);
+        assert!(found.validate().is_ok());
+
+        let score = prefix.validate().unwrap().score_end;
+        let found = Oracle::default()
+            .search(&prefix, Goal::Score(score + 100))
+            .unwrap()
+            .unwrap();
+        assert!(found.validate().unwrap().score_end >= score + 100);
+    }
+
+    #[test]
+    fn luck_ratio() {
+        let oracle = Oracle::default();
+        let mut human: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        human.moves.truncate(human.moves.len() / 2);
+        let ratio = oracle.luck_ratio(&human).unwrap().unwrap();
+        assert!(ratio < 1.0, "{ratio}");
+
+        let planned = oracle
+            .search(
+                &SeededRecording::empty(human.seed, 4, 4),
+                Goal::Moves(human.moves.len()),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(oracle.luck_ratio(&planned).unwrap(), Some(1.0));
+    }
+
+    #[test]
+    fn gives_up() {
+        let prefix = SeededRecording::empty(0, 4, 4);
+        let oracle = Oracle::new(4, 10, WeightedSum::default());
+        assert!(oracle.search(&prefix, Goal::Tile(2048)).unwrap().is_none());
+    }
+
+    #[test]
+    fn unsupported() {
+        let prefix = SeededRecording::empty(0, 5, 5);
+        assert!(matches!(
+            Oracle::default().search(&prefix, Goal::Moves(1)),
+            Err(OracleError::Unsupported(5, 5))
+        ));
+    }
+}
@@ -0,0 +1,272 @@
+//! Command-line interface to the [unified](twothousand_forty_eight::unified) module.
+//!
+//! Every command reads recordings from a file (or stdin), one per line, and prints one result per recording.
+//! With `--json` each result is printed as a single line of JSON. The exit code is 1 if any recording failed.
+
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    process::ExitCode,
+};
+
+use anyhow::Context;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::json;
+use twothousand_forty_eight::{
+    unified::{self, game::GameState, ParseResult},
+    v2::explicit::ExplicitRecording,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "2048",
+    version,
+    about = "Validate and inspect recorded games of 2048"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Print each result as a line of JSON
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Validate recordings, printing their scores
+    Validate(Input),
+    /// Print the hash of each recording
+    Hash {
+        #[command(flatten)]
+        input: Input,
+        /// Hash the positions of the game instead, giving the same hash for the same game in every version
+        #[arg(long)]
+        position: bool,
+    },
+    /// Print the board after every move
+    Replay(Input),
+    /// Print the state of the game at the end of each recording as JSON
+    State(Input),
+    /// Convert recordings to another encoding
+    Convert {
+        #[command(flatten)]
+        input: Input,
+        /// The encoding to convert to
+        #[arg(long, value_enum)]
+        to: Encoding,
+    },
+}
+
+#[derive(clap::Args)]
+struct Input {
+    /// File to read the recordings from, one per line. Reads stdin if missing or `-`
+    file: Option<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Encoding {
+    /// The text format of the recording's version
+    Text,
+    /// The serde representation of the parsed recording
+    Json,
+    /// The compact format with explicit spawns, only for v1 recordings
+    Explicit,
+}
+
+impl Command {
+    fn input(&self) -> &Input {
+        match self {
+            Command::Validate(input)
+            | Command::Hash { input, .. }
+            | Command::Replay(input)
+            | Command::State(input)
+            | Command::Convert { input, .. } => input,
+        }
+    }
+
+    fn run(&self, data: &str) -> anyhow::Result<Output> {
+        match self {
+            Command::Validate(_) => validate(data),
+            Command::Hash { position, .. } => hash(data, *position),
+            Command::Replay(_) => replay(data),
+            Command::State(_) => state(data),
+            Command::Convert { to, .. } => convert(data, *to),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let lines = match read_lines(cli.command.input().file.as_deref()) {
+        Ok(lines) => lines,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut failed = false;
+    for (index, line) in lines.iter().enumerate() {
+        let line_number = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match cli.command.run(line.trim()) {
+            Ok(output) => output.print(line_number, cli.json),
+            Err(e) => {
+                failed = true;
+                if cli.json {
+                    println!(
+                        "{}",
+                        json!({ "line": line_number, "ok": false, "error": e.to_string() })
+                    );
+                } else {
+                    println!("{}: error: {}", line_number, e);
+                }
+            }
+        }
+    }
+    match failed {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
+
+fn read_lines(file: Option<&str>) -> anyhow::Result<Vec<String>> {
+    let reader: Box<dyn Read> = match file {
+        None | Some("-") => Box::new(io::stdin()),
+        Some(path) => {
+            Box::new(std::fs::File::open(path).with_context(|| format!("can't open {}", path))?)
+        }
+    };
+    Ok(BufReader::new(reader).lines().collect::<Result<_, _>>()?)
+}
+
+/// The result of a command for a single recording
+struct Output {
+    /// Printed without `--json`
+    text: String,
+    /// Printed with `--json`, under the `result` key
+    json: serde_json::Value,
+}
+
+impl Output {
+    fn print(&self, line_number: usize, json: bool) {
+        if json {
+            println!(
+                "{}",
+                json!({ "line": line_number, "ok": true, "result": self.json })
+            );
+        } else {
+            println!("{}: {}", line_number, self.text);
+        }
+    }
+}
+
+fn validate(data: &str) -> anyhow::Result<Output> {
+    let result = unified::validate(data)?;
+    Ok(Output {
+        text: format!(
+            "valid, score {} (end {}), {} breaks",
+            result.score, result.score_end, result.breaks
+        ),
+        json: serde_json::to_value(result)?,
+    })
+}
+
+fn hash(data: &str, position: bool) -> anyhow::Result<Output> {
+    let hash = if position {
+        unified::position_hash(data)?
+    } else {
+        unified::hash(data)?
+    };
+    Ok(Output {
+        json: json!(hash),
+        text: hash,
+    })
+}
+
+fn replay(data: &str) -> anyhow::Result<Output> {
+    let reconstruction = unified::reconstruct(data)?;
+    let mut text = String::new();
+    for (index, board) in reconstruction.history.iter().enumerate() {
+        text += &format!("\nmove {}:\n{}", index, board);
+    }
+    Ok(Output {
+        text,
+        json: serde_json::to_value(reconstruction)?,
+    })
+}
+
+fn state(data: &str) -> anyhow::Result<Output> {
+    let state: GameState = unified::get_gamestate(data)?;
+    let json = serde_json::to_value(state)?;
+    Ok(Output {
+        text: json.to_string(),
+        json,
+    })
+}
+
+fn convert(data: &str, to: Encoding) -> anyhow::Result<Output> {
+    // Lines in the JSON encoding are converted back, anything else is parsed as text
+    let parsed: ParseResult = match data.starts_with('{') {
+        true => serde_json::from_str(data)?,
+        false => unified::parse(data)?,
+    };
+    let converted = match to {
+        Encoding::Text => String::from(&parsed),
+        Encoding::Json => serde_json::to_string(&parsed)?,
+        Encoding::Explicit => match &parsed {
+            ParseResult::V1(recording) => String::from(&ExplicitRecording::from(recording)),
+            ParseResult::Explicit(recording) => String::from(recording),
+            ParseResult::V2(_) => {
+                anyhow::bail!("seeded recordings can't be converted to explicit spawns")
+            }
+        },
+    };
+    Ok(Output {
+        json: json!(converted),
+        text: converted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME_V2: &str = include_str!("../../games/v2/Ni4FIRM.txt");
+
+    #[test]
+    fn commands() {
+        let data = GAME_V2.lines().next().unwrap();
+        assert!(validate(data).unwrap().text.starts_with("valid"));
+        assert_eq!(
+            hash(data, false).unwrap().text,
+            unified::hash(data).unwrap()
+        );
+        assert!(hash(data, true).unwrap().text.starts_with('P'));
+        assert!(replay(data).unwrap().json["history"].is_array());
+        assert!(state(data).unwrap().json["board"].is_object());
+        assert!(validate("::2:garbage").is_err());
+    }
+
+    #[test]
+    fn convert_round_trip() {
+        let data = GAME_V2.lines().next().unwrap();
+        let json = convert(data, Encoding::Json).unwrap().text;
+        assert_eq!(convert(&json, Encoding::Text).unwrap().text, data);
+        assert!(convert(data, Encoding::Explicit).is_err());
+    }
+
+    #[test]
+    fn convert_explicit() {
+        let data = include_str!("../../games/v1/GAME3X3A.txt").trim();
+        let explicit = convert(data, Encoding::Explicit).unwrap().text;
+        assert!(explicit.starts_with("::4:3:3:"));
+        assert_eq!(
+            hash(&explicit, false).unwrap().text,
+            hash(data, false).unwrap().text
+        );
+        assert_eq!(convert(&explicit, Encoding::Text).unwrap().text, explicit);
+    }
+}
@@ -0,0 +1,467 @@
+//! Provides [BitBoard], a packed alternative to [Board] used on the fast replay path.
+//!
+//! Every cell is stored as a 4-bit exponent (0 for an empty cell, `n` for a tile with the value `2^n`),
+//! and every row of the board is packed into a single [u32]. Moves are resolved one row (or column) at
+//! a time: rows of up to four cells are looked up from a precomputed table, wider rows are resolved
+//! with the same routine that builds the table.
+//!
+//! A [BitBoard] doesn't know anything about tile ids, convert it to a [Board] when those are needed.
+
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    tile::{SpawnWeight, Tile, CLASSIC_SPAWN_WEIGHTS},
+    Board, MoveError, Tiles,
+};
+use crate::{
+    direction::Direction,
+    random::{Pickable, RandAlgo},
+};
+
+/// Max width of a board that can be packed, limited by the amount of cells that fit in a [u32]
+pub const MAX_PACKED_WIDTH: usize = 8;
+/// Max height of a board that can be packed
+pub const MAX_PACKED_HEIGHT: usize = 8;
+
+/// Largest exponent that fits in a cell, i.e. a tile with the value of 32768
+const MAX_EXPONENT: u32 = 0xF;
+const CELL_BITS: usize = 4;
+const CELL_MASK: u32 = 0xF;
+
+/// Largest amount of cells for which [BitBoard::check_move] is known to agree with [check_move](super::check_move).
+///
+/// Up to 36 cells the original stops after a fixed amount of move checks, which can run out before every tile has slid,
+/// e.g. on 6x6 boards, and existing recordings of those games depend on that behaviour.
+/// Bigger boards get enough checks, but packed boards aren't used for them yet.
+pub const MAX_EXACT_CELLS: usize = 16;
+
+/// Rows at most this wide are resolved with [ROW_TABLE]
+const TABLE_WIDTH: usize = 4;
+
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BitBoardError {
+    #[error("a {0}x{1} board is too big to be packed")]
+    SizeTooBig(usize, usize),
+    #[error("a tile with the value {0} can't be packed")]
+    UnpackableValue(usize),
+    #[error(transparent)]
+    Move(#[from] MoveError),
+}
+
+/// A packed representation of a [Board]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BitBoard {
+    /// The width of the board
+    pub width: usize,
+
+    /// The height of the board
+    pub height: usize,
+
+    /// The cell exponents, four bits per cell, `x = 0` in the lowest bits
+    rows: [u32; MAX_PACKED_HEIGHT],
+
+    /// State of the random number generator
+    pub rng_state: u32,
+
+    /// The random number generator used to add tiles
+    pub rng: RandAlgo,
+}
+
+/// The outcome of moving a single row towards its start
+#[derive(Debug, Clone, Copy, Default)]
+struct RowMove {
+    row: u32,
+    score: u32,
+    /// Set if a merge would produce a tile that doesn't fit in a cell
+    overflow: bool,
+}
+
+static ROW_TABLE: OnceLock<Vec<RowMove>> = OnceLock::new();
+
+fn row_table() -> &'static [RowMove] {
+    ROW_TABLE.get_or_init(|| {
+        (0..1u32 << (TABLE_WIDTH * CELL_BITS))
+            .map(|row| slide_row(row, TABLE_WIDTH))
+            .collect()
+    })
+}
+
+fn get_cell(row: u32, x: usize) -> u32 {
+    (row >> (x * CELL_BITS)) & CELL_MASK
+}
+
+fn with_cell(row: u32, x: usize, exponent: u32) -> u32 {
+    (row & !(CELL_MASK << (x * CELL_BITS))) | (exponent << (x * CELL_BITS))
+}
+
+fn reverse_row(row: u32, width: usize) -> u32 {
+    (0..width).fold(0, |out, x| with_cell(out, width - 1 - x, get_cell(row, x)))
+}
+
+/// Move all the tiles of a row towards `x = 0`, merging equal neighbours once
+fn slide_row(row: u32, width: usize) -> RowMove {
+    let mut out = RowMove::default();
+    let mut target = 0;
+    let mut mergeable = false;
+    for x in 0..width {
+        let exponent = get_cell(row, x);
+        if exponent == 0 {
+            continue;
+        }
+        if mergeable && get_cell(out.row, target - 1) == exponent {
+            if exponent == MAX_EXPONENT {
+                out.overflow = true;
+            }
+            out.row = with_cell(out.row, target - 1, (exponent + 1) & CELL_MASK);
+            out.score += 1 << (exponent + 1);
+            mergeable = false;
+        } else {
+            out.row = with_cell(out.row, target, exponent);
+            target += 1;
+            mergeable = true;
+        }
+    }
+    out
+}
+
+fn move_row(row: u32, width: usize) -> RowMove {
+    if width <= TABLE_WIDTH {
+        row_table()[row as usize]
+    } else {
+        slide_row(row, width)
+    }
+}
+
+fn exponent_of(value: usize) -> Result<u32, BitBoardError> {
+    if value == 0 {
+        return Ok(0);
+    }
+    let exponent = value.trailing_zeros();
+    if value < 2 || !value.is_power_of_two() || exponent > MAX_EXPONENT {
+        return Err(BitBoardError::UnpackableValue(value));
+    }
+    Ok(exponent)
+}
+
+impl BitBoard {
+    /// Create a new empty board with a specified width and height
+    pub fn new(width: usize, height: usize, seed: u32) -> Result<BitBoard, BitBoardError> {
+        if width > MAX_PACKED_WIDTH || height > MAX_PACKED_HEIGHT {
+            return Err(BitBoardError::SizeTooBig(width, height));
+        }
+        Ok(BitBoard {
+            width,
+            height,
+            rows: [0; MAX_PACKED_HEIGHT],
+            rng_state: seed,
+            rng: RandAlgo::LCG,
+        })
+    }
+
+    /// Get the value of the tile at the given position, 0 if the cell is empty
+    pub fn get_value(&self, x: usize, y: usize) -> usize {
+        match get_cell(self.rows[y], x) {
+            0 => 0,
+            exponent => 1 << exponent,
+        }
+    }
+
+    /// Get the exponent of the tile at the given position, i.e. `log2(value)`, 0 if the cell is empty
+    pub fn get_exponent(&self, x: usize, y: usize) -> u32 {
+        get_cell(self.rows[y], x)
+    }
+
+    /// Set the value of the tile at the given position
+    pub fn set_value(&mut self, x: usize, y: usize, value: usize) -> Result<(), BitBoardError> {
+        self.rows[y] = with_cell(self.rows[y], x, exponent_of(value)?);
+        Ok(())
+    }
+
+    /// Get the combined value of all the tiles
+    pub fn get_total_value(&self) -> usize {
+        self.positions().map(|(x, y)| self.get_value(x, y)).sum()
+    }
+
+    /// Get the value of the biggest tile, 0 if the board is empty
+    pub fn get_max_value(&self) -> usize {
+        self.positions()
+            .map(|(x, y)| self.get_value(x, y))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Check if a move in any direction is possible
+    pub fn has_possible_moves(&self) -> bool {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let exponent = get_cell(self.rows[y], x);
+                if exponent == 0 {
+                    return true;
+                }
+                if x + 1 < self.width && get_cell(self.rows[y], x + 1) == exponent {
+                    return true;
+                }
+                if y + 1 < self.height && get_cell(self.rows[y + 1], x) == exponent {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Add a random tile exactly like [Board::add_random_tile] would, consuming the same random numbers
+    pub fn add_random_tile(&mut self) {
+        // The classic values can always be packed
+        self.add_random_tile_with(&CLASSIC_SPAWN_WEIGHTS).unwrap();
+    }
+
+    /// Add a random tile exactly like [Board::add_random_tile_with] would.
+    ///
+    /// Fails without changing the board if the picked value can't be packed.
+    pub fn add_random_tile_with(&mut self, weights: &[SpawnWeight]) -> Result<(), BitBoardError> {
+        let possible = self.get_empty_positions();
+        if !possible.is_empty() {
+            let mut rng_state = self.rng_state;
+            let (x, y) = *possible.pick(&self.rng, &mut rng_state);
+            let value = Tile::random_value_weighted(&self.rng, weights, &mut rng_state);
+            self.rows[y] = with_cell(self.rows[y], x, exponent_of(value)?);
+            self.rng_state = rng_state;
+        }
+        Ok(())
+    }
+
+    /// Get the positions of the empty cells in the same order as [Board::get_non_occupied_tiles]
+    pub fn get_empty_positions(&self) -> Vec<(usize, usize)> {
+        self.positions()
+            .filter(|(x, y)| get_cell(self.rows[*y], *x) == 0)
+            .collect()
+    }
+
+    /// Set every tile with a value lower than `threshold` to zero
+    pub fn clear_below(&mut self, threshold: usize) {
+        for (x, y) in self.positions().collect::<Vec<_>>() {
+            if self.get_value(x, y) < threshold {
+                self.rows[y] = with_cell(self.rows[y], x, 0);
+            }
+        }
+    }
+
+    /// Move the board in the direction "dir" and return the score gained from the move
+    pub fn move_in_direction(&mut self, dir: Direction) -> Result<usize, BitBoardError> {
+        let (board, score_gain) = self.check_move(dir)?;
+        *self = board;
+        Ok(score_gain)
+    }
+
+    /// Packed counterpart of [check_move](super::check_move), returns the moved board and the score gained.
+    ///
+    /// Like the original, [Direction::BREAK] and [Direction::START] are resolved as [Direction::LEFT].
+    pub fn check_move(&self, dir: Direction) -> Result<(BitBoard, usize), BitBoardError> {
+        if dir == Direction::END {
+            return Ok((*self, 0));
+        }
+        if !self.has_possible_moves() {
+            return Err(MoveError::NoValidMovesLeft.into());
+        }
+
+        let mut board = *self;
+        let mut score = 0;
+        let mut overflow = false;
+        match dir {
+            Direction::UP | Direction::DOWN => {
+                for x in 0..self.width {
+                    let column = self.get_column(x);
+                    let moved = self.move_line(column, self.height, dir == Direction::DOWN);
+                    board.set_column(x, moved.row);
+                    score += moved.score as usize;
+                    overflow |= moved.overflow;
+                }
+            }
+            _ => {
+                for y in 0..self.height {
+                    let moved = self.move_line(self.rows[y], self.width, dir == Direction::RIGHT);
+                    board.rows[y] = moved.row;
+                    score += moved.score as usize;
+                    overflow |= moved.overflow;
+                }
+            }
+        }
+
+        if overflow {
+            return Err(BitBoardError::UnpackableValue(1 << (MAX_EXPONENT + 1)));
+        }
+        if board.rows == self.rows {
+            return Err(MoveError::HasNoEffect.into());
+        }
+        Ok((board, score))
+    }
+
+    fn move_line(&self, line: u32, length: usize, reverse: bool) -> RowMove {
+        if reverse {
+            let moved = move_row(reverse_row(line, length), length);
+            RowMove {
+                row: reverse_row(moved.row, length),
+                ..moved
+            }
+        } else {
+            move_row(line, length)
+        }
+    }
+
+    fn get_column(&self, x: usize) -> u32 {
+        (0..self.height).fold(0, |out, y| with_cell(out, y, get_cell(self.rows[y], x)))
+    }
+
+    fn set_column(&mut self, x: usize, column: u32) {
+        for y in 0..self.height {
+            self.rows[y] = with_cell(self.rows[y], x, get_cell(column, y));
+        }
+    }
+
+    /// Iterate over all the positions of the board in the same order as [Board::get_all_tiles]
+    fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.width, self.height);
+        (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+}
+
+impl TryFrom<&Board> for BitBoard {
+    type Error = BitBoardError;
+
+    fn try_from(board: &Board) -> Result<Self, Self::Error> {
+        let mut packed = BitBoard::new(board.width, board.height, board.rng_state)?;
+        packed.rng = board.rng;
+        for tile in board.get_occupied_tiles() {
+            packed.set_value(tile.x, tile.y, tile.value)?;
+        }
+        Ok(packed)
+    }
+}
+
+/// Materialize the tiles of the board, assigning new ids in the same order as [Board::new]
+impl From<&BitBoard> for Board {
+    fn from(packed: &BitBoard) -> Self {
+        let mut board =
+            Board::new_with_rng(packed.width, packed.height, packed.rng_state, packed.rng);
+        for (x, y) in packed.positions() {
+            if let Some(tile) = board.tiles[y][x].as_mut() {
+                tile.value = packed.get_value(x, y);
+            }
+        }
+        board
+    }
+}
+
+impl From<&BitBoard> for Tiles {
+    fn from(packed: &BitBoard) -> Self {
+        Board::from(packed).tiles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::check_move, direction::REAL_DIRECTIONS, random::lcg_sane};
+
+    fn random_board(width: usize, height: usize, seed: u32) -> Board {
+        let mut board = Board::new(width, height, seed);
+        let mut rng = seed;
+        for y in 0..height {
+            for x in 0..width {
+                let exponent = lcg_sane(&mut rng) % 7;
+                if exponent > 0 && exponent < 6 {
+                    board.set_tile(x, y, 1 << exponent);
+                }
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn round_trip() {
+        let board = random_board(4, 4, 1234);
+        let packed = BitBoard::try_from(&board).unwrap();
+        let unpacked = Board::from(&packed);
+        assert_eq!(unpacked.get_all_tiles(), board.get_all_tiles());
+        assert_eq!(unpacked.rng_state, board.rng_state);
+    }
+
+    #[test]
+    fn rejects_unpackable_values() {
+        let mut board = Board::new(4, 4, 0);
+        board.set_tile(0, 0, 3);
+        assert_eq!(
+            BitBoard::try_from(&board),
+            Err(BitBoardError::UnpackableValue(3))
+        );
+        board.set_tile(0, 0, 65536);
+        assert_eq!(
+            BitBoard::try_from(&board),
+            Err(BitBoardError::UnpackableValue(65536))
+        );
+    }
+
+    #[test]
+    fn overflowing_merge() {
+        let mut packed = BitBoard::new(4, 4, 0).unwrap();
+        packed.set_value(0, 0, 32768).unwrap();
+        packed.set_value(1, 0, 32768).unwrap();
+        assert!(matches!(
+            packed.check_move(Direction::LEFT),
+            Err(BitBoardError::UnpackableValue(_))
+        ));
+    }
+
+    #[test]
+    fn matches_check_move() {
+        for (width, height) in [(2, 2), (3, 3), (4, 4), (3, 5), (2, 6)] {
+            assert!(width * height <= MAX_EXACT_CELLS);
+            for seed in 0..1000 {
+                let board = random_board(width, height, seed);
+                let packed = BitBoard::try_from(&board).unwrap();
+                for dir in REAL_DIRECTIONS.into_iter().chain([Direction::END]) {
+                    match (check_move(board.clone(), dir), packed.check_move(dir)) {
+                        (Ok(expected), Ok((moved, score_gain))) => {
+                            assert_eq!(
+                                Board::from(&moved).get_all_tiles(),
+                                expected.board.get_all_tiles(),
+                                "{width}x{height} seed {seed} {dir:?}"
+                            );
+                            assert_eq!(score_gain, expected.score_gain);
+                        }
+                        (Err(expected), Err(e)) => assert_eq!(BitBoardError::Move(expected), e),
+                        (expected, actual) => panic!(
+                            "{width}x{height} seed {seed} {dir:?}: {:?} != {:?}",
+                            expected.map(|r| r.score_gain),
+                            actual.map(|r| r.1)
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_add_random_tile() {
+        for rng in [
+            RandAlgo::LCG,
+            RandAlgo::Xorshift32,
+            RandAlgo::Pcg32,
+            RandAlgo::SplitMix32,
+        ] {
+            let mut board = Board::new_with_rng(4, 4, 42, rng);
+            let mut packed = BitBoard::try_from(&board).unwrap();
+            assert_eq!(packed.rng, rng);
+            for _ in 0..17 {
+                board.add_random_tile();
+                packed.add_random_tile();
+                assert_eq!(Board::from(&packed).get_all_tiles(), board.get_all_tiles());
+                assert_eq!(packed.rng_state, board.rng_state);
+            }
+            assert_eq!(Board::from(&packed).rng, rng);
+        }
+    }
+}
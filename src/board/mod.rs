@@ -1,24 +1,25 @@
 //! Provides [Board] to hold game board data and [tile] to hold the values of the board tiles.
+//!
+//! [bitboard] provides a packed representation of the board for faster replays and
+//! [trace] describes the changes a move made to the tiles.
 
+pub mod bitboard;
 pub mod tile;
+pub mod trace;
 use std::fmt::Display;
 
-use crate::direction::Direction;
+use crate::{direction::Direction, random::RandAlgo};
 use serde::{Deserialize, Serialize};
 use tile::Tile;
 
 use self::tile::InitialID;
 
-/// Max width of a board the program can handle. Be careful when increasing, as this increases memory use expotentially.
-pub const MAX_WIDTH: usize = 6;
-/// Max height of a board the program can handle. Be careful when increasing, as this increases memory use expotentially.
-pub const MAX_HEIGHT: usize = 6;
-
+/// The tiles of a board as rows, indexed with `tiles[y][x]`. The rows are allocated based on the size of the board.
 #[cfg_attr(feature = "wasm", tsify::declare)]
-pub type Tiles = [[Option<Tile>; MAX_WIDTH]; MAX_HEIGHT];
+pub type Tiles = Vec<Vec<Option<Tile>>>;
 
 /// Holds game board data
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
 #[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
 pub struct Board {
@@ -28,12 +29,16 @@ pub struct Board {
     /// The height of the board. Value of 0 is untested
     pub height: usize,
 
-    /// The tiles of the board, note that the size of the array is allocated based on the max size.
+    /// The tiles of the board, `height` rows of `width` tiles each.
     pub tiles: Tiles,
 
     /// State of the random number generator
     pub rng_state: u32,
 
+    /// The random number generator used to add tiles
+    #[serde(default)]
+    pub rng: RandAlgo,
+
     /// State of the id generator, the last id that was assigned
     id_counter: usize,
 }
@@ -41,26 +46,29 @@ pub struct Board {
 impl Board {
     /// Create a new board with a specified width and height and initialize all tiles
     pub fn new(width: usize, height: usize, seed: u32) -> Board {
+        Board::new_with_rng(width, height, seed, RandAlgo::LCG)
+    }
+
+    /// Same as [Board::new], but tiles are added with the random number generator `rng`
+    pub fn new_with_rng(width: usize, height: usize, seed: u32, rng: RandAlgo) -> Board {
         let mut id_counter = 0;
         Board {
             width,
             height,
             tiles: initialize_tiles(width, height, &mut id_counter),
             rng_state: seed,
+            rng,
             id_counter,
         }
     }
 
-    /// Set a tile on the board and silently fail if the target tile doesn't exist.
-    pub fn set_tile(&mut self, x: usize, y: usize, val: usize) {
-        if self.tiles[y][x].is_some() {
-            self.tiles[y][x] = Some(Tile::new(
-                x,
-                y,
-                val,
-                tile::InitialID::AutoAssign(&mut self.id_counter),
-            ));
-        }
+    /// Set a tile on the board and return it, or `None` if the target tile doesn't exist.
+    pub fn set_tile(&mut self, x: usize, y: usize, val: usize) -> Option<Tile> {
+        let cell = self.tiles.get_mut(y)?.get_mut(x)?;
+        cell.as_ref()?;
+        let tile = Tile::new(x, y, val, tile::InitialID::AutoAssign(&mut self.id_counter));
+        *cell = Some(tile);
+        Some(tile)
     }
 
     /// Get the tiles that exist and which's values are non-zero
@@ -155,32 +163,41 @@ impl Board {
     }
 
     pub fn get_random_tile_to_add(&mut self) -> Option<tile::Tile> {
+        self.get_random_tile_to_add_with(&tile::CLASSIC_SPAWN_WEIGHTS)
+    }
+
+    /// Same as [Board::get_random_tile_to_add], with the value picked from `weights`
+    pub fn get_random_tile_to_add_with(
+        &mut self,
+        weights: &[tile::SpawnWeight],
+    ) -> Option<tile::Tile> {
         use crate::random::Pickable;
 
         let possible = self.get_non_occupied_tiles();
         if !possible.is_empty() {
-            let t = possible.pick_lcg(&mut self.rng_state);
+            let t = possible.pick(&self.rng, &mut self.rng_state);
 
-            let value = tile::Tile::random_value(&mut self.rng_state);
+            let value = tile::Tile::random_value_weighted(&self.rng, weights, &mut self.rng_state);
 
             return Some(tile::Tile::new(t.x, t.y, value, tile::InitialID::Id(t.id)));
         }
         None
     }
 
-    pub fn add_random_tile(&mut self) {
-        let possible_t = self.get_random_tile_to_add();
-        match possible_t {
-            None => {}
-            Some(t) => {
-                self.set_tile(t.x, t.y, t.value);
-            }
-        }
+    /// Add a random tile to the board and return it, if there was space left for one
+    pub fn add_random_tile(&mut self) -> Option<Tile> {
+        self.add_random_tile_with(&tile::CLASSIC_SPAWN_WEIGHTS)
+    }
+
+    /// Same as [Board::add_random_tile], with the value picked from `weights`
+    pub fn add_random_tile_with(&mut self, weights: &[tile::SpawnWeight]) -> Option<Tile> {
+        let t = self.get_random_tile_to_add_with(weights)?;
+        self.set_tile(t.x, t.y, t.value)
     }
 
     /// Move the board in the direction "dir" and return the score gained from the move
     pub fn move_in_direction(&mut self, dir: Direction) -> Result<usize, MoveError> {
-        let result = check_move(*self, dir);
+        let result = check_move(self.clone(), dir);
         result.map(|data| {
             *self = data.board;
             data.score_gain
@@ -218,6 +235,7 @@ impl From<(Tiles, u32)> for Board {
             height,
             tiles,
             rng_state,
+            rng: RandAlgo::LCG,
             id_counter: largest_id + 1,
         }
     }
@@ -247,10 +265,7 @@ impl Display for Board {
 
 /// Initialize an array of empty tiles created with [Tile::new]
 pub fn initialize_tiles(width: usize, height: usize, id_counter: &mut usize) -> Tiles {
-    if width > MAX_WIDTH || height > MAX_HEIGHT {
-        panic!("Board size too big! This version of the program has been compiled to support the maximum size of {:?}", (MAX_WIDTH, MAX_HEIGHT));
-    }
-    let mut tiles: Tiles = [[None; MAX_WIDTH]; MAX_HEIGHT];
+    let mut tiles: Tiles = vec![vec![None; width]; height];
     for x in 0..width {
         for y in 0..height {
             tiles[y][x] = Some(Tile::new(x, y, 0, tile::InitialID::AutoAssign(id_counter)));
@@ -371,7 +386,24 @@ pub fn get_farthest_tile(
     farthest
 }
 
+/// The fixed limit on merge or slide checks of the original boards, which recordings of those sizes depend on
 const MAX_MOVE_CHECKS: usize = 256;
+/// The most cells a board had before the limit started scaling with its size
+const MAX_FIXED_CHECK_CELLS: usize = 36;
+
+/// The most merge or slide checks a move can take on `board`.
+///
+/// Boards of up to 36 cells, like 6x6, keep [MAX_MOVE_CHECKS], even though it can run out before every tile has slid.
+/// On bigger ones every slide restarts the checks, and each tile slides at most once per cell it passes, so the bound
+/// grows with the amount of cells times the longer side of the board.
+fn max_move_checks(board: &Board) -> usize {
+    let cells = board.width * board.height;
+    if cells <= MAX_FIXED_CHECK_CELLS {
+        return MAX_MOVE_CHECKS;
+    }
+    (cells * board.width.max(board.height) + 1) * (cells + 1)
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct MoveResult {
     pub board: Board,
@@ -416,8 +448,9 @@ pub fn check_move(board: Board, dir: Direction) -> Result<MoveResult, MoveError>
     let mut score = 0;
 
     // Merge
+    let max_checks = max_move_checks(&board);
     let mut ids_checked_for_merge: Vec<usize> = vec![];
-    for _ in 0..MAX_MOVE_CHECKS {
+    for _ in 0..max_checks {
         let occupied_tiles = board.get_occupied_tiles();
         let viable_tiles: Vec<Tile> = occupied_tiles
             .iter()
@@ -458,7 +491,7 @@ pub fn check_move(board: Board, dir: Direction) -> Result<MoveResult, MoveError>
 
     // Slide
     let mut moved_tiles: Vec<usize> = vec![];
-    for _ in 0..MAX_MOVE_CHECKS {
+    for _ in 0..max_checks {
         let tiles_post = board.get_occupied_tiles();
 
         if let Some(t) = tiles_post.iter().find(|t| !moved_tiles.contains(&t.id)) {
@@ -519,7 +552,7 @@ mod tests {
 
     #[test]
     fn from_tuple() {
-        let board = Board::from(([[None; MAX_WIDTH]; MAX_HEIGHT], 0));
+        let board = Board::from((vec![], 0));
         assert_eq!(board.width, 0);
         assert_eq!(board.height, 0);
     }
@@ -539,6 +572,118 @@ mod tests {
         ensure_no_same_ids(&board);
     }
 
+    fn board_from_rows(rows: &[Vec<usize>]) -> Board {
+        let mut board = Board::new(rows[0].len(), rows.len(), 0);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, value) in row.iter().enumerate() {
+                board.set_tile(x, y, *value);
+            }
+        }
+        board
+    }
+
+    fn rows(board: &Board) -> Vec<Vec<usize>> {
+        board
+            .tiles
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|t| t.map(|t| t.value).unwrap_or(0))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn large_spawn_weights() {
+        let weights = [
+            tile::SpawnWeight {
+                value: 2,
+                weight: u32::MAX,
+            },
+            tile::SpawnWeight {
+                value: 4,
+                weight: u32::MAX,
+            },
+        ];
+        let mut seed = 1234;
+        for _ in 0..100 {
+            let value = Tile::random_value_weighted(&crate::random::Lcg, &weights, &mut seed);
+            assert!(value == 2 || value == 4);
+        }
+    }
+
+    #[test]
+    fn set_tile_outside_board() {
+        let mut board = Board::new(4, 4, 0);
+        assert_eq!(board.set_tile(3, 3, 2).map(|t| t.value), Some(2));
+        assert_eq!(board.set_tile(4, 0, 2), None);
+        assert_eq!(board.set_tile(0, 4, 2), None);
+    }
+
+    #[test]
+    fn move_checks_only_scale_on_big_boards() {
+        for (width, height) in [(4, 4), (5, 5), (6, 6), (4, 9)] {
+            assert_eq!(
+                max_move_checks(&Board::new(width, height, 0)),
+                MAX_MOVE_CHECKS
+            );
+        }
+        assert!(max_move_checks(&Board::new(7, 7, 0)) > MAX_MOVE_CHECKS);
+    }
+
+    #[test]
+    fn moves_on_7x7() {
+        let board = board_from_rows(&[
+            vec![0, 2, 0, 2, 0, 4, 0],
+            vec![2, 0, 4, 0, 8, 0, 16],
+            vec![0, 0, 0, 0, 0, 0, 2],
+            vec![4, 4, 4, 4, 0, 0, 0],
+            vec![0, 8, 0, 0, 8, 0, 8],
+            vec![2, 4, 2, 4, 2, 4, 2],
+            vec![0, 0, 0, 2, 0, 0, 0],
+        ]);
+        let moved = check_move(board, Direction::LEFT).unwrap();
+        assert_eq!(
+            rows(&moved.board),
+            vec![
+                vec![4, 4, 0, 0, 0, 0, 0],
+                vec![2, 4, 8, 16, 0, 0, 0],
+                vec![2, 0, 0, 0, 0, 0, 0],
+                vec![8, 8, 0, 0, 0, 0, 0],
+                vec![16, 8, 0, 0, 0, 0, 0],
+                vec![2, 4, 2, 4, 2, 4, 2],
+                vec![2, 0, 0, 0, 0, 0, 0],
+            ]
+        );
+        assert_eq!(moved.score_gain, 4 + 8 + 8 + 16);
+    }
+
+    #[test]
+    fn moves_on_8x8() {
+        let board = board_from_rows(&vec![vec![0, 2, 0, 4, 0, 8, 0, 16]; 8]);
+        let moved = check_move(board.clone(), Direction::LEFT).unwrap();
+        assert_eq!(rows(&moved.board), vec![vec![2, 4, 8, 16, 0, 0, 0, 0]; 8]);
+        assert_eq!(moved.score_gain, 0);
+
+        let moved = check_move(board, Direction::DOWN).unwrap();
+        let mut expected = vec![vec![0, 0, 0, 0, 0, 0, 0, 0]; 4];
+        expected.extend(vec![vec![0, 4, 0, 8, 0, 16, 0, 32]; 4]);
+        assert_eq!(rows(&moved.board), expected);
+        assert_eq!(moved.score_gain, 4 * (4 + 8 + 16 + 32));
+    }
+
+    #[test]
+    fn moves_on_10x10() {
+        let board = board_from_rows(&vec![vec![2, 0, 2, 0, 4, 0, 0, 8, 0, 8]; 10]);
+        let moved = check_move(board, Direction::RIGHT).unwrap();
+        assert_eq!(
+            rows(&moved.board),
+            vec![vec![0, 0, 0, 0, 0, 0, 0, 4, 4, 16]; 10]
+        );
+        assert_eq!(moved.score_gain, 10 * (4 + 16));
+    }
+
     #[test]
     fn no_equal_ids_on_play() {
         let mut board = Board::default();
@@ -2,7 +2,7 @@ use std::num::Wrapping;
 
 use serde::{Deserialize, Serialize};
 
-use crate::random::Pickable;
+use crate::random::{Pickable, Rng};
 
 /// Tile is a basic representation of the tiles on the game board.
 #[allow(clippy::derived_hash_with_manual_eq)]
@@ -27,6 +27,30 @@ pub struct Tile {
     pub new: bool,
 }
 
+/// The values a new tile can get, each entry is as likely to be picked
+pub const SPAWN_VALUES: [usize; 4] = [2, 2, 2, 4];
+
+/// A value a new tile can get, along with how likely it is compared to the other values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SpawnWeight {
+    pub value: usize,
+    pub weight: u32,
+}
+
+/// [SPAWN_VALUES] as weights, picking from these consumes the same random numbers
+pub const CLASSIC_SPAWN_WEIGHTS: [SpawnWeight; 2] = [
+    SpawnWeight {
+        value: 2,
+        weight: 3,
+    },
+    SpawnWeight {
+        value: 4,
+        weight: 1,
+    },
+];
+
 pub enum InitialID<'a> {
     Id(usize),
     AutoAssign(&'a mut usize),
@@ -74,8 +98,33 @@ impl Tile {
     }
 
     pub fn random_value(seed: &mut u32) -> usize {
-        let possible_values: [usize; 4] = [2, 2, 2, 4];
-        *possible_values.pick_lcg(seed)
+        *SPAWN_VALUES.pick_lcg(seed)
+    }
+
+    /// Pick a value from `weights` with the random number generator `rng`.
+    ///
+    /// Works like picking from a list where every value is repeated `weight` times, so
+    /// [CLASSIC_SPAWN_WEIGHTS] gives the same values as [Tile::random_value].
+    /// An empty list (or one where every weight is zero) always gives 2, without using a random number.
+    /// Weights past a total of [u32::MAX] can't be reached, [ConfigurableRuleset](crate::rules::configurable::ConfigurableRuleset) rejects them.
+    pub fn random_value_weighted<R: Rng + ?Sized>(
+        rng: &R,
+        weights: &[SpawnWeight],
+        seed: &mut u32,
+    ) -> usize {
+        // Summed as u64 so that large weights can't overflow
+        let total: u64 = weights.iter().map(|w| u64::from(w.weight)).sum();
+        if total == 0 {
+            return 2;
+        }
+        let mut rnd = u64::from(rng.next(seed)) % total;
+        for w in weights {
+            if rnd < u64::from(w.weight) {
+                return w.value;
+            }
+            rnd -= u64::from(w.weight);
+        }
+        unreachable!("the random number is always smaller than the total weight")
     }
 
     pub fn compare(a: &Self, b: &Self) -> bool {
@@ -0,0 +1,191 @@
+//! Provides [MoveEvent] to describe how the tiles of a [Board] changed during a move, e.g. for animating it.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{check_move, tile::Tile, Board, MoveError};
+use crate::direction::Direction;
+
+/// A single change to the tiles of a board. Positions are given as `(x, y)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum MoveEvent {
+    /// The tile `id` slid from `from` to `to`
+    Slide {
+        id: usize,
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+    /// The tiles in `from` were merged into a new tile `into` with the value `value`
+    Merge {
+        from: [usize; 2],
+        into: usize,
+        position: (usize, usize),
+        value: usize,
+    },
+    /// A new tile `id` with the value `value` was added to the board
+    Spawn {
+        id: usize,
+        position: (usize, usize),
+        value: usize,
+    },
+}
+
+impl MoveEvent {
+    /// Create a [MoveEvent::Spawn] from a freshly added tile
+    pub fn spawn(tile: &Tile) -> MoveEvent {
+        MoveEvent::Spawn {
+            id: tile.id,
+            position: (tile.x, tile.y),
+            value: tile.value,
+        }
+    }
+}
+
+/// The result of [check_move_traced]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracedMoveResult {
+    pub board: Board,
+    pub score_gain: usize,
+    pub events: Vec<MoveEvent>,
+}
+
+/// Check if a move is possible in the direction "dir" and list the events that happened during it.
+///
+/// The events never include a [MoveEvent::Spawn], see [Board::add_random_tile] for that.
+pub fn check_move_traced(board: Board, dir: Direction) -> Result<TracedMoveResult, MoveError> {
+    let result = check_move(board.clone(), dir)?;
+    let events = trace_move(&board, &result.board);
+    Ok(TracedMoveResult {
+        board: result.board,
+        score_gain: result.score_gain,
+        events,
+    })
+}
+
+/// List the events that turned `before` into `after`, where `after` is the result of [check_move] on `before`.
+///
+/// Slides come first in the order of [Board::get_occupied_tiles] on `before`, followed by the merges
+/// in the order of [Board::get_occupied_tiles] on `after`.
+/// Tiles that were merged slide to the position of the tile they were merged into.
+pub fn trace_move(before: &Board, after: &Board) -> Vec<MoveEvent> {
+    let mut destinations: HashMap<usize, (usize, usize)> = HashMap::new();
+    let mut merges = vec![];
+    for tile in after.get_occupied_tiles() {
+        let position = (tile.x, tile.y);
+        match tile.merged_from {
+            Some(from) => {
+                for id in from {
+                    destinations.insert(id, position);
+                }
+                merges.push(MoveEvent::Merge {
+                    from,
+                    into: tile.id,
+                    position,
+                    value: tile.value,
+                });
+            }
+            None => {
+                destinations.insert(tile.id, position);
+            }
+        }
+    }
+
+    let mut events: Vec<MoveEvent> = before
+        .get_occupied_tiles()
+        .iter()
+        .filter_map(|tile| {
+            let from = (tile.x, tile.y);
+            let to = *destinations.get(&tile.id)?;
+            (from != to).then_some(MoveEvent::Slide {
+                id: tile.id,
+                from,
+                to,
+            })
+        })
+        .collect();
+    events.extend(merges);
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slide_and_merge() {
+        let mut board = Board::new(4, 4, 0);
+        board.set_tile(1, 0, 4);
+        board.set_tile(3, 0, 4);
+        board.set_tile(2, 2, 2);
+        let a = board.tiles[0][1].unwrap();
+        let b = board.tiles[0][3].unwrap();
+        let c = board.tiles[2][2].unwrap();
+
+        let result = check_move_traced(board, Direction::LEFT).unwrap();
+        let merged = result.board.tiles[0][0].unwrap();
+        assert_eq!(result.score_gain, 8);
+        assert_eq!(merged.value, 8);
+
+        assert_eq!(result.events.len(), 4);
+        for id in [a.id, b.id] {
+            assert!(result.events.contains(&MoveEvent::Slide {
+                id,
+                from: if id == a.id { (1, 0) } else { (3, 0) },
+                to: (0, 0),
+            }));
+        }
+        assert!(result.events.contains(&MoveEvent::Slide {
+            id: c.id,
+            from: (2, 2),
+            to: (0, 2),
+        }));
+        let Some(MoveEvent::Merge {
+            from,
+            into,
+            position,
+            value,
+        }) = result.events.last().copied()
+        else {
+            panic!("expected the last event to be a merge");
+        };
+        assert!(from == [a.id, b.id] || from == [b.id, a.id]);
+        assert_eq!(into, merged.id);
+        assert_eq!(position, (0, 0));
+        assert_eq!(value, 8);
+    }
+
+    #[test]
+    fn stationary_tiles() {
+        let mut board = Board::new(4, 4, 0);
+        board.set_tile(0, 0, 2);
+        board.set_tile(3, 3, 2);
+        let moving = board.tiles[3][3].unwrap();
+        let result = check_move_traced(board, Direction::UP).unwrap();
+        assert_eq!(
+            result.events,
+            vec![MoveEvent::Slide {
+                id: moving.id,
+                from: (3, 3),
+                to: (3, 0),
+            }]
+        );
+    }
+
+    #[test]
+    fn spawn() {
+        let mut board = Board::new(4, 4, 0);
+        let tile = board.add_random_tile().unwrap();
+        assert_eq!(board.tiles[tile.y][tile.x], Some(tile));
+        assert_eq!(
+            MoveEvent::spawn(&tile),
+            MoveEvent::Spawn {
+                id: tile.id,
+                position: (tile.x, tile.y),
+                value: tile.value,
+            }
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! Encodings shared by the recording formats and the [attestation](crate::unified::attestation) and
+//! [commitment](crate::unified::commitment) protocols
+
+use base64::engine::general_purpose::{GeneralPurpose, STANDARD_NO_PAD};
+
+pub(crate) fn get_b64_engine() -> GeneralPurpose {
+    STANDARD_NO_PAD
+}
+
+/// The start of a signed or hashed message. The domain keeps a message of one kind from passing as another, and the
+/// id is prefixed with its length as it may contain anything, while the fields after it can't contain the zero byte
+/// separating them.
+pub(crate) fn message_header(domain: &str, id: &str) -> String {
+    format!("{domain}\0{}:{id}", id.len())
+}
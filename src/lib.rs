@@ -2,14 +2,17 @@
 //!
 //! Includes wasm bindings generated with wasm_bindgen
 
+pub mod ai;
 #[allow(clippy::needless_range_loop)]
 pub mod board;
 pub mod direction;
+mod encoding;
 pub mod random;
 pub mod rules;
 pub mod unified;
 pub mod v1;
 pub mod v2;
+pub mod version;
 
 #[cfg(feature = "wasm")]
 pub mod wasm;
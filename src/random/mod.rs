@@ -6,6 +6,8 @@ use std::num::Wrapping;
 
 use serde::{Deserialize, Serialize};
 
+use crate::version;
+
 // https://en.wikipedia.org/wiki/Linear_congruential_generator
 pub fn linear_congruential_generator(m: u32, a: u32, c: u32, seed: &mut u32) -> u32 {
     // Wrapping is used to allow overflow
@@ -18,29 +20,208 @@ pub fn lcg_sane(seed: &mut u32) -> u32 {
     linear_congruential_generator(2147483647, 1103515245, 12345, seed)
 }
 
+/// A pseudo-random number generator that keeps all of its state in a single `u32`, e.g. [Board::rng_state](crate::board::Board::rng_state)
+pub trait Rng {
+    /// Advance `state` and return the next number
+    fn next(&self, state: &mut u32) -> u32;
+}
+
+/// The original generator, [lcg_sane]. Its low quality is part of every existing recording.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lcg;
+
+impl Rng for Lcg {
+    fn next(&self, state: &mut u32) -> u32 {
+        lcg_sane(state)
+    }
+}
+
+/// Marsaglia's 32-bit xorshift. A state of 0 would get stuck, so it is replaced with a fixed constant.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Xorshift32;
+
+impl Rng for Xorshift32 {
+    fn next(&self, state: &mut u32) -> u32 {
+        let mut x = if *state == 0 { 0x9E3779B9 } else { *state };
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        *state = x;
+        x
+    }
+}
+
+// https://www.pcg-random.org/
+/// PCG with 32 bits of state and the RXS-M-XS output function
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pcg32;
+
+impl Rng for Pcg32 {
+    fn next(&self, state: &mut u32) -> u32 {
+        let old = *state;
+        *state = old.wrapping_mul(747796405).wrapping_add(2891336453);
+        let word = ((old >> ((old >> 28) + 4)) ^ old).wrapping_mul(277803737);
+        (word >> 22) ^ word
+    }
+}
+
+/// A 32-bit variant of SplitMix, a Weyl sequence passed through a bit mixer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SplitMix32;
+
+impl Rng for SplitMix32 {
+    fn next(&self, state: &mut u32) -> u32 {
+        *state = state.wrapping_add(0x9E3779B9);
+        let mut z = *state;
+        z = (z ^ (z >> 16)).wrapping_mul(0x85EBCA6B);
+        z = (z ^ (z >> 13)).wrapping_mul(0xC2B2AE35);
+        z ^ (z >> 16)
+    }
+}
+
 pub trait Pickable<T> {
-    fn pick_lcg(&self, seed: &mut u32) -> &T;
+    fn pick<R: Rng + ?Sized>(&self, rng: &R, seed: &mut u32) -> &T;
+
+    fn pick_lcg(&self, seed: &mut u32) -> &T {
+        self.pick(&Lcg, seed)
+    }
 }
 impl<T> Pickable<T> for Vec<T> {
-    fn pick_lcg(&self, seed: &mut u32) -> &T {
-        let max = self.len() as u32;
-        let rnd = lcg_sane(seed);
-
-        let index: u32 = rnd % max;
-        &self[index as usize]
+    fn pick<R: Rng + ?Sized>(&self, rng: &R, seed: &mut u32) -> &T {
+        self.as_slice().pick(rng, seed)
     }
 }
 impl<T, const SIZE: usize> Pickable<T> for [T; SIZE] {
-    fn pick_lcg(&self, seed: &mut u32) -> &T {
+    fn pick<R: Rng + ?Sized>(&self, rng: &R, seed: &mut u32) -> &T {
+        self.as_slice().pick(rng, seed)
+    }
+}
+impl<T> Pickable<T> for [T] {
+    fn pick<R: Rng + ?Sized>(&self, rng: &R, seed: &mut u32) -> &T {
         let max = self.len() as u32;
-        let rnd = lcg_sane(seed);
+        let rnd = rng.next(seed);
 
         let index: u32 = rnd % max;
         &self[index as usize]
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The random number generators available to the game
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
 pub enum RandAlgo {
+    #[default]
     LCG = 0,
+    Xorshift32 = 1,
+    Pcg32 = 2,
+    SplitMix32 = 3,
+}
+
+impl Rng for RandAlgo {
+    fn next(&self, state: &mut u32) -> u32 {
+        match self {
+            RandAlgo::LCG => Lcg.next(state),
+            RandAlgo::Xorshift32 => Xorshift32.next(state),
+            RandAlgo::Pcg32 => Pcg32.next(state),
+            RandAlgo::SplitMix32 => SplitMix32.next(state),
+        }
+    }
+}
+
+impl RandAlgo {
+    /// The name of the generator, as written in the [rng metadata](crate::v2::metadata::Metadata::RNG) of a recording
+    pub fn name(&self) -> &'static str {
+        match self {
+            RandAlgo::LCG => "lcg",
+            RandAlgo::Xorshift32 => "xorshift32",
+            RandAlgo::Pcg32 => "pcg32",
+            RandAlgo::SplitMix32 => "splitmix32",
+        }
+    }
+
+    /// The generator with the [name](RandAlgo::name)
+    pub fn from_name(name: &str) -> Option<RandAlgo> {
+        [
+            RandAlgo::LCG,
+            RandAlgo::Xorshift32,
+            RandAlgo::Pcg32,
+            RandAlgo::SplitMix32,
+        ]
+        .into_iter()
+        .find(|algo| algo.name() == name)
+    }
+}
+
+/// Get the random number generator a [version](crate::version) of the [v2](crate::v2) recording format uses by default.
+///
+/// From version 3 onwards a recording can pick another one with its [metadata](crate::v2::metadata::Metadata::RNG).
+/// Returns `None` for the versions that don't use one.
+pub fn for_version(version: u8) -> Option<RandAlgo> {
+    match version {
+        version::SEEDED | version::SEEDED_METADATA => Some(RandAlgo::LCG),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALGOS: [RandAlgo; 4] = [
+        RandAlgo::LCG,
+        RandAlgo::Xorshift32,
+        RandAlgo::Pcg32,
+        RandAlgo::SplitMix32,
+    ];
+
+    #[test]
+    fn lcg_is_unchanged() {
+        let (mut a, mut b) = (1234, 1234);
+        for _ in 0..1000 {
+            assert_eq!(RandAlgo::LCG.next(&mut a), lcg_sane(&mut b));
+        }
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn names() {
+        for algo in ALGOS {
+            assert_eq!(RandAlgo::from_name(algo.name()), Some(algo));
+        }
+        assert_eq!(RandAlgo::from_name("mersenne"), None);
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(for_version(version::V1), None);
+        assert_eq!(for_version(version::SEEDED), Some(RandAlgo::LCG));
+        assert_eq!(for_version(version::SEEDED_METADATA), Some(RandAlgo::LCG));
+        assert_eq!(for_version(version::EXPLICIT), None);
+    }
+
+    #[test]
+    fn deterministic() {
+        for algo in ALGOS {
+            let (mut a, mut b) = (42, 42);
+            let first: Vec<u32> = (0..100).map(|_| algo.next(&mut a)).collect();
+            let second: Vec<u32> = (0..100).map(|_| algo.next(&mut b)).collect();
+            assert_eq!(first, second);
+        }
+    }
+
+    #[test]
+    fn roughly_uniform() {
+        const OPTIONS: [usize; 10] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        for algo in ALGOS {
+            let mut seed = 0;
+            let mut count = [0; OPTIONS.len()];
+            for _ in 0..100_000 {
+                count[*OPTIONS.pick(&algo, &mut seed)] += 1;
+            }
+            for c in count {
+                assert!((9_000..11_000).contains(&c), "{:?}: {:?}", algo, count);
+            }
+        }
+    }
 }
@@ -0,0 +1,443 @@
+//! Provides [ConfigurableRuleset], a [Ruleset] defined by data instead of code.
+//!
+//! A ruleset can be written in JSON, or in TOML with the `toml` feature enabled:
+//! ```toml
+//! id = "big-win"
+//! win_tile = 4096
+//! break_max = 1
+//!
+//! [[break_costs]]
+//! width = 4
+//! height = 4
+//! cost = 500
+//!
+//! [[spawn_weights]]
+//! value = 2
+//! weight = 1
+//!
+//! [[spawn_weights]]
+//! value = 4
+//! weight = 1
+//! ```
+//! Every field except `id` has a default matching [ClassicV2](super::ClassicV2).
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::Ruleset;
+use crate::{
+    board::{
+        bitboard::{MAX_PACKED_HEIGHT, MAX_PACKED_WIDTH},
+        tile::{SpawnWeight, CLASSIC_SPAWN_WEIGHTS},
+        Board,
+    },
+    unified::validation::MAX_ALLOWED_BREAKS,
+};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RulesetError {
+    #[error("failed to parse the ruleset: {0}")]
+    Parse(String),
+
+    #[error("the ruleset needs an id")]
+    MissingId,
+
+    #[error("the win tile must be a power of two of at least 4, got {0}")]
+    InvalidWinTile(usize),
+
+    #[error("the break tile threshold must be a power of two, got {0}")]
+    InvalidBreakThreshold(usize),
+
+    #[error("at most {1} breaks can be allowed, got {0}")]
+    TooManyBreaks(usize, usize),
+
+    #[error("the break cost of a {0}x{1} board is defined more than once")]
+    DuplicateBreakCost(usize, usize),
+
+    #[error("at least one spawn value needs a weight above zero")]
+    NoSpawnWeights,
+
+    #[error("the spawn weights can add up to at most {}", u32::MAX)]
+    SpawnWeightsTooLarge,
+
+    #[error("spawned tiles must be powers of two of at least 2, got {0}")]
+    InvalidSpawnValue(usize),
+
+    #[error("at least one tile must be spawned per move")]
+    NoSpawnsPerMove,
+
+    #[error("at most {1} tiles can be spawned at once, got {0}")]
+    TooManySpawns(usize, usize),
+}
+
+/// Most tiles a ruleset can add at once, as many as the largest packable board has cells.
+/// Rulesets come with recordings, so this keeps one from making a replay spawn tiles practically forever.
+pub const MAX_SPAWNED_TILES: usize = MAX_PACKED_WIDTH * MAX_PACKED_HEIGHT;
+
+/// The cost of a break on boards of a specific size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(deny_unknown_fields)]
+pub struct BreakCost {
+    pub width: usize,
+    pub height: usize,
+    pub cost: usize,
+}
+
+/// A [Ruleset] loaded from a spec, see the [module documentation](self) for the format.
+///
+/// Use [ConfigurableRuleset::validate] (or one of the `from_*` constructors) before using a ruleset built by hand.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(deny_unknown_fields)]
+pub struct ConfigurableRuleset {
+    /// Names the ruleset. Recordings are hashed with the whole [canonical](Self::canonical) spec, not just the id.
+    pub id: String,
+    /// The game is won once a tile of at least this value is on the board
+    #[serde(default = "default_win_tile")]
+    pub win_tile: usize,
+    /// Amount of breaks allowed per game
+    #[serde(default = "default_break_max")]
+    pub break_max: usize,
+    /// Tiles with a value lower than this are removed by a break
+    #[serde(default = "default_break_tile_threshold")]
+    pub break_tile_threshold: usize,
+    /// The cost of a break on boards that aren't listed in `break_costs`
+    #[serde(default = "default_break_cost")]
+    pub default_break_cost: usize,
+    /// The cost of a break per board size
+    #[serde(default = "default_break_costs")]
+    pub break_costs: Vec<BreakCost>,
+    /// The values new tiles can get and how likely each of them is
+    #[serde(default = "default_spawn_weights")]
+    pub spawn_weights: Vec<SpawnWeight>,
+    /// Amount of tiles added to the board before the first move
+    #[serde(default = "default_initial_tiles")]
+    pub initial_tiles: usize,
+    /// Amount of tiles added to the board after each move
+    #[serde(default = "default_spawns_per_move")]
+    pub spawns_per_move: usize,
+}
+
+fn default_win_tile() -> usize {
+    2048
+}
+fn default_break_max() -> usize {
+    3
+}
+fn default_break_tile_threshold() -> usize {
+    16
+}
+fn default_break_cost() -> usize {
+    2500
+}
+fn default_spawn_weights() -> Vec<SpawnWeight> {
+    CLASSIC_SPAWN_WEIGHTS.to_vec()
+}
+fn default_initial_tiles() -> usize {
+    2
+}
+fn default_spawns_per_move() -> usize {
+    1
+}
+fn default_break_costs() -> Vec<BreakCost> {
+    let mut costs = vec![];
+    for (width, height, cost) in [
+        (2, 2, 100),
+        (2, 3, 250),
+        (3, 3, 500),
+        (3, 4, 750),
+        (4, 4, 1000),
+        (4, 5, 1250),
+        (5, 5, 1500),
+        (5, 6, 1750),
+        (6, 6, 2000),
+    ] {
+        costs.push(BreakCost {
+            width,
+            height,
+            cost,
+        });
+        if width != height {
+            costs.push(BreakCost {
+                width: height,
+                height: width,
+                cost,
+            });
+        }
+    }
+    costs
+}
+
+impl ConfigurableRuleset {
+    /// A ruleset with the given id and the defaults for everything else
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            win_tile: default_win_tile(),
+            break_max: default_break_max(),
+            break_tile_threshold: default_break_tile_threshold(),
+            default_break_cost: default_break_cost(),
+            break_costs: default_break_costs(),
+            spawn_weights: default_spawn_weights(),
+            initial_tiles: default_initial_tiles(),
+            spawns_per_move: default_spawns_per_move(),
+        }
+    }
+
+    /// The spec as JSON, with the break costs sorted so that rulesets that behave the same are written the same way.
+    ///
+    /// Unlike the [id](Self::id), this covers everything the ruleset does, so it's what identifies the ruleset of a recording.
+    pub fn canonical(&self) -> String {
+        let mut ruleset = self.clone();
+        ruleset
+            .break_costs
+            .sort_by_key(|entry| (entry.width, entry.height));
+        serde_json::to_string(&ruleset).expect("a ruleset can always be written as JSON")
+    }
+
+    /// Parse and validate a ruleset from JSON
+    pub fn from_json(data: &str) -> Result<Self, RulesetError> {
+        let ruleset: Self =
+            serde_json::from_str(data).map_err(|e| RulesetError::Parse(e.to_string()))?;
+        ruleset.validate()?;
+        Ok(ruleset)
+    }
+
+    /// Parse and validate a ruleset from TOML
+    #[cfg(feature = "toml")]
+    pub fn from_toml(data: &str) -> Result<Self, RulesetError> {
+        let ruleset: Self = toml::from_str(data).map_err(|e| RulesetError::Parse(e.to_string()))?;
+        ruleset.validate()?;
+        Ok(ruleset)
+    }
+
+    /// Check that the ruleset makes sense and fits within the limits of the validators
+    pub fn validate(&self) -> Result<(), RulesetError> {
+        if self.id.is_empty() {
+            return Err(RulesetError::MissingId);
+        }
+        if self.win_tile < 4 || !self.win_tile.is_power_of_two() {
+            return Err(RulesetError::InvalidWinTile(self.win_tile));
+        }
+        if !self.break_tile_threshold.is_power_of_two() {
+            return Err(RulesetError::InvalidBreakThreshold(
+                self.break_tile_threshold,
+            ));
+        }
+        if self.break_max > MAX_ALLOWED_BREAKS {
+            return Err(RulesetError::TooManyBreaks(
+                self.break_max,
+                MAX_ALLOWED_BREAKS,
+            ));
+        }
+        for (index, entry) in self.break_costs.iter().enumerate() {
+            let duplicate = self.break_costs[..index]
+                .iter()
+                .any(|other| other.width == entry.width && other.height == entry.height);
+            if duplicate {
+                return Err(RulesetError::DuplicateBreakCost(entry.width, entry.height));
+            }
+        }
+        if self.spawn_weights.iter().all(|w| w.weight == 0) {
+            return Err(RulesetError::NoSpawnWeights);
+        }
+        if self
+            .spawn_weights
+            .iter()
+            .try_fold(0u32, |total, w| total.checked_add(w.weight))
+            .is_none()
+        {
+            return Err(RulesetError::SpawnWeightsTooLarge);
+        }
+        for w in &self.spawn_weights {
+            if w.value < 2 || !w.value.is_power_of_two() {
+                return Err(RulesetError::InvalidSpawnValue(w.value));
+            }
+        }
+        if self.spawns_per_move == 0 {
+            return Err(RulesetError::NoSpawnsPerMove);
+        }
+        for count in [self.initial_tiles, self.spawns_per_move] {
+            if count > MAX_SPAWNED_TILES {
+                return Err(RulesetError::TooManySpawns(count, MAX_SPAWNED_TILES));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Ruleset for ConfigurableRuleset {
+    fn break_cost(&self, board: &Board) -> usize {
+        self.break_costs
+            .iter()
+            .find(|entry| entry.width == board.width && entry.height == board.height)
+            .map(|entry| entry.cost)
+            .unwrap_or(self.default_break_cost)
+    }
+    fn break_max(&self, _board: &Board) -> usize {
+        self.break_max
+    }
+    fn break_tile_threshold(&self, _board: &Board) -> usize {
+        self.break_tile_threshold
+    }
+
+    fn game_over(&self, board: &Board) -> bool {
+        !board.has_possible_moves()
+    }
+
+    fn won(&self, board: &Board) -> bool {
+        board
+            .get_all_tiles()
+            .iter()
+            .any(|t| t.value >= self.win_tile)
+    }
+
+    fn spawn_weights(&self, _board: &Board) -> &[SpawnWeight] {
+        &self.spawn_weights
+    }
+    fn initial_tiles(&self, _board: &Board) -> usize {
+        self.initial_tiles
+    }
+    fn spawns_per_move(&self, _board: &Board) -> usize {
+        self.spawns_per_move
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::ClassicV2;
+
+    #[test]
+    fn defaults_match_classic_v2() {
+        let rules = ConfigurableRuleset::from_json(r#"{ "id": "classic" }"#).unwrap();
+        for width in 1..=8 {
+            for height in 1..=8 {
+                let board = Board::new(width, height, 0);
+                assert_eq!(rules.break_cost(&board), ClassicV2.break_cost(&board));
+                assert_eq!(rules.break_max(&board), ClassicV2.break_max(&board));
+                assert_eq!(
+                    rules.break_tile_threshold(&board),
+                    ClassicV2.break_tile_threshold(&board)
+                );
+            }
+        }
+        assert_eq!(rules, ConfigurableRuleset::new("classic"));
+    }
+
+    #[test]
+    fn custom() {
+        let rules = ConfigurableRuleset::from_json(
+            r#"{
+                "id": "big-win",
+                "win_tile": 4096,
+                "break_max": 1,
+                "default_break_cost": 10,
+                "break_costs": [{ "width": 4, "height": 4, "cost": 500 }]
+            }"#,
+        )
+        .unwrap();
+        let mut board = Board::new(4, 4, 0);
+        assert_eq!(rules.break_cost(&board), 500);
+        assert_eq!(rules.break_cost(&Board::new(3, 3, 0)), 10);
+        assert_eq!(rules.break_max(&board), 1);
+        board.set_tile(0, 0, 2048);
+        assert!(!rules.won(&board));
+        board.set_tile(0, 0, 4096);
+        assert!(rules.won(&board));
+    }
+
+    #[test]
+    fn invalid() {
+        let parse = |data: &str| ConfigurableRuleset::from_json(data).unwrap_err();
+        assert!(matches!(
+            parse(r#"{ "id": "a", "typo": 1 }"#),
+            RulesetError::Parse(_)
+        ));
+        assert_eq!(parse(r#"{ "id": "" }"#), RulesetError::MissingId);
+        assert_eq!(
+            parse(r#"{ "id": "a", "win_tile": 1000 }"#),
+            RulesetError::InvalidWinTile(1000)
+        );
+        assert_eq!(
+            parse(r#"{ "id": "a", "break_tile_threshold": 15 }"#),
+            RulesetError::InvalidBreakThreshold(15)
+        );
+        assert_eq!(
+            parse(r#"{ "id": "a", "break_max": 4 }"#),
+            RulesetError::TooManyBreaks(4, MAX_ALLOWED_BREAKS)
+        );
+        assert_eq!(
+            parse(
+                r#"{ "id": "a", "break_costs": [
+                    { "width": 4, "height": 4, "cost": 1 },
+                    { "width": 4, "height": 4, "cost": 2 }
+                ] }"#
+            ),
+            RulesetError::DuplicateBreakCost(4, 4)
+        );
+        assert_eq!(
+            parse(r#"{ "id": "a", "spawn_weights": [{ "value": 2, "weight": 0 }] }"#),
+            RulesetError::NoSpawnWeights
+        );
+        assert_eq!(
+            parse(
+                r#"{ "id": "a", "spawn_weights": [
+                    { "value": 2, "weight": 4294967295 },
+                    { "value": 4, "weight": 1 }
+                ] }"#
+            ),
+            RulesetError::SpawnWeightsTooLarge
+        );
+        assert_eq!(
+            parse(r#"{ "id": "a", "spawn_weights": [{ "value": 3, "weight": 1 }] }"#),
+            RulesetError::InvalidSpawnValue(3)
+        );
+        assert_eq!(
+            parse(r#"{ "id": "a", "spawns_per_move": 0 }"#),
+            RulesetError::NoSpawnsPerMove
+        );
+        assert_eq!(
+            parse(r#"{ "id": "a", "spawns_per_move": 18446744073709551615 }"#),
+            RulesetError::TooManySpawns(usize::MAX, MAX_SPAWNED_TILES)
+        );
+        assert_eq!(
+            parse(r#"{ "id": "a", "initial_tiles": 65 }"#),
+            RulesetError::TooManySpawns(65, MAX_SPAWNED_TILES)
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml() {
+        let rules = ConfigurableRuleset::from_toml(
+            r#"
+            id = "big-win"
+            win_tile = 4096
+            break_max = 1
+
+            [[break_costs]]
+            width = 4
+            height = 4
+            cost = 500
+
+            [[spawn_weights]]
+            value = 2
+            weight = 1
+
+            [[spawn_weights]]
+            value = 4
+            weight = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules.win_tile, 4096);
+        assert_eq!(rules.break_costs.len(), 1);
+        assert_eq!(rules.break_cost(&Board::new(4, 4, 0)), 500);
+        assert_eq!(rules.spawn_weights.len(), 2);
+        assert!(rules.spawn_weights.iter().all(|w| w.weight == 1));
+    }
+}
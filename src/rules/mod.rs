@@ -1,8 +1,19 @@
 //! Rulesets for the game.
 //!
 //! Note that these are only really usable with the [v2](crate::v2) interface.
+//!
+//! [configurable] provides a ruleset that is loaded from data instead of being defined in code.
+
+pub mod configurable;
 
-use crate::board::Board;
+use crate::{
+    board::{
+        tile::{SpawnWeight, CLASSIC_SPAWN_WEIGHTS},
+        Board,
+    },
+    unified::validation::MAX_ALLOWED_BREAKS,
+    version,
+};
 
 pub trait Ruleset {
     fn break_cost(&self, board: &Board) -> usize;
@@ -10,14 +21,44 @@ pub trait Ruleset {
     fn break_tile_threshold(&self, board: &Board) -> usize;
     fn game_over(&self, board: &Board) -> bool;
     fn won(&self, board: &Board) -> bool;
+    /// The values new tiles can get and how likely each of them is.
+    ///
+    /// The spawn rules may only depend on the size of the board, the packed replay only looks them up once per game.
+    fn spawn_weights(&self, board: &Board) -> &[SpawnWeight];
+    /// Amount of tiles added to the board before the first move
+    fn initial_tiles(&self, board: &Board) -> usize;
+    /// Amount of tiles added to the board after each move
+    fn spawns_per_move(&self, board: &Board) -> usize;
 }
 
 pub trait RulesetProvider {
     fn rules(&self) -> &dyn Ruleset;
 }
 
+/// Get the rules used by a [version](crate::version) of the recording format
+pub fn for_version(version: u8) -> &'static dyn Ruleset {
+    match version {
+        // Explicit recordings are converted from v1 ones
+        version::V1 | version::EXPLICIT => &ClassicV1,
+        version::SEEDED | version::SEEDED_METADATA => &ClassicV2,
+        _ => &ClassicV2, // we should probably panic here
+    }
+}
+
+/// The amount of breaks allowed by `rules`, capped to what a [ValidationResult](crate::unified::validation::ValidationResult) can hold
+pub fn break_max(rules: &dyn Ruleset, board: &Board) -> usize {
+    rules.break_max(board).min(MAX_ALLOWED_BREAKS)
+}
+
 pub fn can_break(rules: &dyn Ruleset, board: &Board, score: usize, breaks: usize) -> bool {
-    breaks < rules.break_max(board) && rules.break_cost(board) <= score
+    breaks < break_max(rules, board) && rules.break_cost(board) <= score
+}
+
+/// Add `count` random tiles to the board according to the spawn weights of `rules`
+pub fn spawn_tiles(rules: &dyn Ruleset, board: &mut Board, count: usize) {
+    for _ in 0..count {
+        board.add_random_tile_with(rules.spawn_weights(board));
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -41,6 +82,16 @@ impl Ruleset for ClassicV1 {
     fn won(&self, board: &Board) -> bool {
         board.get_all_tiles().iter().any(|t| t.value >= 2048)
     }
+
+    fn spawn_weights(&self, _board: &Board) -> &[SpawnWeight] {
+        &CLASSIC_SPAWN_WEIGHTS
+    }
+    fn initial_tiles(&self, _board: &Board) -> usize {
+        2
+    }
+    fn spawns_per_move(&self, _board: &Board) -> usize {
+        1
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -75,6 +126,16 @@ impl Ruleset for ClassicV2 {
     fn won(&self, board: &Board) -> bool {
         board.get_all_tiles().iter().any(|t| t.value >= 2048)
     }
+
+    fn spawn_weights(&self, _board: &Board) -> &[SpawnWeight] {
+        &CLASSIC_SPAWN_WEIGHTS
+    }
+    fn initial_tiles(&self, _board: &Board) -> usize {
+        2
+    }
+    fn spawns_per_move(&self, _board: &Board) -> usize {
+        1
+    }
 }
 
 #[cfg(test)]
@@ -103,6 +164,14 @@ mod tests {
         assert!(!rules.won(&board));
     }
 
+    #[test]
+    fn break_cost_big_boards() {
+        let rules = ClassicV2;
+        assert_eq!(rules.break_cost(&Board::new(6, 6, 0)), 2000);
+        assert_eq!(rules.break_cost(&Board::new(7, 7, 0)), 2500);
+        assert_eq!(rules.break_cost(&Board::new(8, 8, 0)), 2500);
+    }
+
     #[test]
     fn breaks_v1() {
         let rules = ClassicV1;
@@ -0,0 +1,334 @@
+//! Proof that a game was started from a seed issued by a server and submitted as is.
+//!
+//! The server signs a [SeedAttestation] when it hands out a seed, and a [SubmissionAttestation] over the
+//! [game hash](super::hash::Hashable::game_hash) when the finished recording is submitted.
+//! Anyone holding the [VerifyingKey] can then check both with [verify].
+//!
+//! Signatures are either HMAC-SHA256 with a shared secret or Ed25519, in which case the verifying key can be public.
+
+use std::fmt;
+
+use base64::Engine;
+use ed25519_dalek::{Signer, Verifier};
+use hmac::{Mac, NewMac};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::hash::Hashable;
+use crate::{
+    encoding::{get_b64_engine, message_header},
+    v2::recording::SeededRecording,
+};
+
+const SEED_DOMAIN: &str = "2048-seed";
+const SUBMISSION_DOMAIN: &str = "2048-submission";
+
+type HmacSha256 = hmac::Hmac<sha2::Sha256>;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AttestationError {
+    #[error("invalid key")]
+    InvalidKey,
+    #[error("invalid signature encoding")]
+    InvalidEncoding,
+    #[error("the signature doesn't match")]
+    InvalidSignature,
+    #[error("the attestations are for different games ({0} and {1})")]
+    IdMismatch(String, String),
+    #[error("the recording doesn't match the issued seed")]
+    SeedMismatch,
+    #[error("the recording doesn't match the submitted hash")]
+    HashMismatch,
+}
+
+/// A key for signing attestations, its [Debug] output leaves out the secret
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum SigningKey {
+    /// A shared secret, which also verifies the signatures
+    HmacSha256(Vec<u8>),
+    /// An Ed25519 secret key
+    Ed25519([u8; 32]),
+}
+
+/// A key for verifying attestations, its [Debug] output leaves out the shared secret
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum VerifyingKey {
+    /// The same shared secret the attestations were signed with
+    HmacSha256(Vec<u8>),
+    /// An Ed25519 public key
+    Ed25519([u8; 32]),
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningKey::HmacSha256(_) => f.write_str("HmacSha256(..)"),
+            SigningKey::Ed25519(_) => f.write_str("Ed25519(..)"),
+        }
+    }
+}
+
+impl fmt::Debug for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyingKey::HmacSha256(_) => f.write_str("HmacSha256(..)"),
+            VerifyingKey::Ed25519(public) => f.debug_tuple("Ed25519").field(public).finish(),
+        }
+    }
+}
+
+impl SigningKey {
+    pub fn verifying_key(&self) -> VerifyingKey {
+        match self {
+            SigningKey::HmacSha256(secret) => VerifyingKey::HmacSha256(secret.clone()),
+            SigningKey::Ed25519(secret) => VerifyingKey::Ed25519(
+                ed25519_dalek::SigningKey::from_bytes(secret)
+                    .verifying_key()
+                    .to_bytes(),
+            ),
+        }
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<String, AttestationError> {
+        let signature = match self {
+            SigningKey::HmacSha256(secret) => {
+                let mut mac =
+                    HmacSha256::new_from_slice(secret).map_err(|_| AttestationError::InvalidKey)?;
+                mac.update(message);
+                mac.finalize().into_bytes().to_vec()
+            }
+            SigningKey::Ed25519(secret) => ed25519_dalek::SigningKey::from_bytes(secret)
+                .sign(message)
+                .to_bytes()
+                .to_vec(),
+        };
+        Ok(get_b64_engine().encode(signature))
+    }
+}
+
+impl VerifyingKey {
+    fn verify(&self, message: &[u8], signature: &str) -> Result<(), AttestationError> {
+        let signature = get_b64_engine()
+            .decode(signature)
+            .map_err(|_| AttestationError::InvalidEncoding)?;
+        match self {
+            VerifyingKey::HmacSha256(secret) => {
+                let mut mac =
+                    HmacSha256::new_from_slice(secret).map_err(|_| AttestationError::InvalidKey)?;
+                mac.update(message);
+                mac.verify(&signature)
+                    .map_err(|_| AttestationError::InvalidSignature)
+            }
+            VerifyingKey::Ed25519(public) => {
+                let key = ed25519_dalek::VerifyingKey::from_bytes(public)
+                    .map_err(|_| AttestationError::InvalidKey)?;
+                let signature = ed25519_dalek::Signature::from_slice(&signature)
+                    .map_err(|_| AttestationError::InvalidSignature)?;
+                key.verify(message, &signature)
+                    .map_err(|_| AttestationError::InvalidSignature)
+            }
+        }
+    }
+}
+
+/// Issued along with the seed when a game is started
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SeedAttestation {
+    /// Identifies the game, chosen by the server
+    pub id: String,
+    pub seed: u32,
+    pub width: usize,
+    pub height: usize,
+    /// Base64 encoded
+    pub signature: String,
+}
+
+/// Issued when a finished game is submitted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SubmissionAttestation {
+    /// The id of the [SeedAttestation] of the game
+    pub id: String,
+    /// The [game hash](Hashable::game_hash) of the submitted recording
+    pub hash: String,
+    /// Base64 encoded
+    pub signature: String,
+}
+
+impl SeedAttestation {
+    /// Sign the seed of a new game
+    pub fn sign(
+        key: &SigningKey,
+        id: &str,
+        seed: u32,
+        width: usize,
+        height: usize,
+    ) -> Result<Self, AttestationError> {
+        Ok(Self {
+            id: id.to_string(),
+            seed,
+            width,
+            height,
+            signature: key.sign(&seed_message(id, seed, width, height))?,
+        })
+    }
+
+    pub fn verify(&self, key: &VerifyingKey) -> Result<(), AttestationError> {
+        key.verify(
+            &seed_message(&self.id, self.seed, self.width, self.height),
+            &self.signature,
+        )
+    }
+
+    /// Whether the recording was started from this seed
+    pub fn matches(&self, recording: &SeededRecording) -> bool {
+        recording.seed == self.seed
+            && recording.width == self.width
+            && recording.height == self.height
+    }
+}
+
+impl SubmissionAttestation {
+    /// Sign the hash of a submitted recording
+    pub fn sign(
+        key: &SigningKey,
+        id: &str,
+        recording: &SeededRecording,
+    ) -> Result<Self, AttestationError> {
+        let hash = recording.game_hash();
+        Ok(Self {
+            id: id.to_string(),
+            signature: key.sign(&submission_message(id, &hash))?,
+            hash,
+        })
+    }
+
+    pub fn verify(&self, key: &VerifyingKey) -> Result<(), AttestationError> {
+        key.verify(&submission_message(&self.id, &self.hash), &self.signature)
+    }
+}
+
+/// Check that the recording was started from the attested seed and is the one that was submitted
+pub fn verify(
+    recording: &SeededRecording,
+    seed: &SeedAttestation,
+    submission: &SubmissionAttestation,
+    key: &VerifyingKey,
+) -> Result<(), AttestationError> {
+    seed.verify(key)?;
+    submission.verify(key)?;
+    if seed.id != submission.id {
+        return Err(AttestationError::IdMismatch(
+            seed.id.clone(),
+            submission.id.clone(),
+        ));
+    }
+    if !seed.matches(recording) {
+        return Err(AttestationError::SeedMismatch);
+    }
+    if recording.game_hash() != submission.hash {
+        return Err(AttestationError::HashMismatch);
+    }
+    Ok(())
+}
+
+fn seed_message(id: &str, seed: u32, width: usize, height: usize) -> Vec<u8> {
+    format!(
+        "{}\0{seed}\0{width}\0{height}",
+        message_header(SEED_DOMAIN, id)
+    )
+    .into_bytes()
+}
+
+fn submission_message(id: &str, hash: &str) -> Vec<u8> {
+    format!("{}\0{hash}", message_header(SUBMISSION_DOMAIN, id)).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{direction::Direction, v2::test_data::GAME_NI4FIRM};
+
+    fn keys() -> [SigningKey; 2] {
+        [
+            SigningKey::HmacSha256(b"server secret".to_vec()),
+            SigningKey::Ed25519([7; 32]),
+        ]
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let recording: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        for key in keys() {
+            let public = key.verifying_key();
+            let seed = SeedAttestation::sign(
+                &key,
+                "game-1",
+                recording.seed,
+                recording.width,
+                recording.height,
+            )
+            .unwrap();
+            let submission = SubmissionAttestation::sign(&key, "game-1", &recording).unwrap();
+            assert_eq!(verify(&recording, &seed, &submission, &public), Ok(()));
+
+            let mut forged = recording.clone();
+            forged.moves.push(Direction::UP);
+            assert_eq!(
+                verify(&forged, &seed, &submission, &public),
+                Err(AttestationError::HashMismatch)
+            );
+
+            let mut forged = seed.clone();
+            forged.seed += 1;
+            assert_eq!(
+                verify(&recording, &forged, &submission, &public),
+                Err(AttestationError::InvalidSignature)
+            );
+
+            let other = SeedAttestation::sign(&key, "game-2", recording.seed, 4, 4).unwrap();
+            assert_eq!(
+                verify(&recording, &other, &submission, &public),
+                Err(AttestationError::IdMismatch(
+                    "game-2".to_string(),
+                    "game-1".to_string()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_key() {
+        let recording: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        let [hmac, ed25519] = keys();
+        let seed = SeedAttestation::sign(&hmac, "game-1", recording.seed, 4, 4).unwrap();
+        assert_eq!(
+            seed.verify(&SigningKey::HmacSha256(b"guess".to_vec()).verifying_key()),
+            Err(AttestationError::InvalidSignature)
+        );
+        assert_eq!(
+            seed.verify(&ed25519.verifying_key()),
+            Err(AttestationError::InvalidSignature)
+        );
+        assert_eq!(
+            seed.verify(&SigningKey::Ed25519([8; 32]).verifying_key()),
+            Err(AttestationError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn debug_hides_secrets() {
+        let [hmac, ed25519] = keys();
+        assert_eq!(format!("{hmac:?}"), "HmacSha256(..)");
+        assert_eq!(format!("{ed25519:?}"), "Ed25519(..)");
+        assert_eq!(format!("{:?}", hmac.verifying_key()), "HmacSha256(..)");
+        assert!(format!("{:?}", ed25519.verifying_key()).starts_with("Ed25519(["));
+    }
+}
@@ -0,0 +1,214 @@
+//! Validating many recordings at once.
+//!
+//! [validate_batch] validates every recording and collects a [GameReport] for each along with [BatchStats] over all of them.
+//! With the `parallel` feature the recordings are validated in parallel with rayon.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    hash::Hashable,
+    validation::{Validatable, ValidationResult},
+    ParseResult,
+};
+
+/// The outcome of validating a single recording
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct GameReport {
+    /// Index of the recording in the batch, starting from 1 to match line numbers
+    pub line: usize,
+    /// The game hash, None if the recording couldn't be parsed
+    pub hash: Option<String>,
+    /// None if the recording isn't valid
+    pub validation: Option<ValidationResult>,
+    /// Why the recording isn't valid
+    pub error: Option<String>,
+}
+
+/// Aggregate statistics of a batch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct BatchStats {
+    pub total: usize,
+    pub invalid: usize,
+    /// How many valid games reached each score, bucketed by the largest power of two not above the score.
+    /// Scores of zero are in the bucket 0.
+    pub score_histogram: BTreeMap<usize, usize>,
+    /// Hashes that appear more than once, along with the lines they appear on
+    pub duplicates: BTreeMap<String, Vec<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct BatchReport {
+    /// A report for every recording, in the order they were given
+    pub games: Vec<GameReport>,
+    pub stats: BatchStats,
+}
+
+/// Validate every recording, in parallel if the `parallel` feature is enabled
+pub fn validate_batch<I, S>(recordings: I) -> BatchReport
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str> + Send,
+{
+    let recordings: Vec<(usize, S)> = recordings
+        .into_iter()
+        .enumerate()
+        .map(|(index, data)| (index + 1, data))
+        .collect();
+
+    #[cfg(feature = "parallel")]
+    let games: Vec<GameReport> = {
+        use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+        recordings
+            .into_par_iter()
+            .map(|(line, data)| report(line, data.as_ref()))
+            .collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let games: Vec<GameReport> = recordings
+        .into_iter()
+        .map(|(line, data)| report(line, data.as_ref()))
+        .collect();
+
+    let stats = stats(&games);
+    BatchReport { games, stats }
+}
+
+fn report(line: usize, data: &str) -> GameReport {
+    let parsed = match super::parse(data) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return GameReport {
+                line,
+                hash: None,
+                validation: None,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+    let (hash, validation) = match &parsed {
+        ParseResult::V1(recording) => (
+            recording.game_hash(),
+            recording.validate().map_err(|e| e.to_string()),
+        ),
+        ParseResult::V2(recording) => (
+            recording.game_hash(),
+            recording.validate().map_err(|e| e.to_string()),
+        ),
+        ParseResult::Explicit(recording) => (
+            recording.game_hash(),
+            recording.validate().map_err(|e| e.to_string()),
+        ),
+    };
+    GameReport {
+        line,
+        hash: Some(hash),
+        validation: validation.as_ref().ok().copied(),
+        error: validation.err(),
+    }
+}
+
+fn stats(games: &[GameReport]) -> BatchStats {
+    let mut stats = BatchStats {
+        total: games.len(),
+        ..Default::default()
+    };
+    let mut lines_by_hash: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for game in games {
+        match &game.validation {
+            Some(validation) => {
+                *stats
+                    .score_histogram
+                    .entry(score_bucket(validation.score))
+                    .or_default() += 1;
+            }
+            None => stats.invalid += 1,
+        }
+        if let Some(hash) = &game.hash {
+            lines_by_hash.entry(hash).or_default().push(game.line);
+        }
+    }
+    stats.duplicates = lines_by_hash
+        .into_iter()
+        .filter(|(_, lines)| lines.len() > 1)
+        .map(|(hash, lines)| (hash.to_string(), lines))
+        .collect();
+    stats
+}
+
+fn score_bucket(score: usize) -> usize {
+    match score {
+        0 => 0,
+        _ => 1 << score.ilog2(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        v1::tests::lib_testgames::{GAME3X3, GAME4X4},
+        v2::test_data::{GAME_INVALID_BREAK_AFTER_LOSS, GAME_NI4FIRM, GAME_WON_3_BREAKS},
+    };
+
+    #[test]
+    fn batch() {
+        let report = validate_batch([GAME_NI4FIRM, GAME4X4, "::2:garbage", GAME_NI4FIRM, GAME3X3]);
+        assert_eq!(report.games.len(), 5);
+        assert_eq!(
+            report.games.iter().map(|g| g.line).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            report.games[0].validation,
+            Some(crate::unified::validate(GAME_NI4FIRM).unwrap())
+        );
+        assert!(report.games[2].hash.is_none() && report.games[2].error.is_some());
+
+        assert_eq!(report.stats.total, 5);
+        assert_eq!(report.stats.invalid, 1);
+        assert_eq!(report.stats.score_histogram.values().sum::<usize>(), 4);
+        assert_eq!(report.stats.score_histogram[&512], 2);
+        assert_eq!(
+            report.stats.duplicates,
+            BTreeMap::from([(crate::unified::hash(GAME_NI4FIRM).unwrap(), vec![1, 4])])
+        );
+    }
+
+    #[test]
+    fn invalid_games_are_hashed() {
+        let report = validate_batch(vec![
+            GAME_WON_3_BREAKS.to_string(),
+            GAME_INVALID_BREAK_AFTER_LOSS.to_string(),
+        ]);
+        assert!(report.games[0].error.is_none());
+        assert!(report.games[1].hash.is_some());
+        assert!(report.games[1].validation.is_none());
+        assert_eq!(report.stats.invalid, 1);
+    }
+
+    #[test]
+    fn malformed_boards_are_reported() {
+        let oversized = "4x4S0.0.0.0.0.0.0.0.0.0.0.0.0.0.2.2.2;1";
+        let report = validate_batch([GAME3X3, oversized, GAME4X4]);
+        assert_eq!(report.games.len(), 3);
+        assert!(report.games[1].error.is_some());
+        assert!(report.games[0].error.is_none() && report.games[2].error.is_none());
+        assert_eq!(report.stats.invalid, 1);
+    }
+
+    #[test]
+    fn buckets() {
+        assert_eq!(score_bucket(0), 0);
+        assert_eq!(score_bucket(1), 1);
+        assert_eq!(score_bucket(604), 512);
+        assert_eq!(score_bucket(1024), 1024);
+    }
+}
@@ -0,0 +1,269 @@
+//! A commit/reveal protocol that keeps players from choosing their own seeds.
+//!
+//! 1. The server picks a random seed and salt, and publishes a [SeedCommitment] to them along with the time it was issued.
+//! 2. The player is given the seed and plays the game.
+//! 3. On submission the server looks up the [IssuedSeed] it stored by the id of the game, and [verify] checks that the
+//!    recording was played from the committed seed and submitted within the allowed time. The [SeedOpening] is revealed.
+//!
+//! The commitment can't be opened to any other seed, so a server can't swap the seed after the fact either.
+//! [LocalIssuer] issues seeds the same way a server would, for testing the protocol locally.
+
+use base64::Engine;
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{
+    encoding::{get_b64_engine, message_header},
+    v2::recording::SeededRecording,
+};
+
+const COMMITMENT_DOMAIN: &str = "2048-commitment";
+const SALT_LENGTH: usize = 16;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CommitmentError {
+    #[error("the opening doesn't match the commitment")]
+    OpeningMismatch,
+    #[error("the recording wasn't played from the committed seed")]
+    SeedMismatch,
+    #[error("submitted at {submitted_at}, before the seed was issued at {issued_at}")]
+    SubmittedBeforeIssue { issued_at: u64, submitted_at: u64 },
+    #[error("submitted {age}s after the seed was issued, the limit is {max_age}s")]
+    Expired { age: u64, max_age: u64 },
+}
+
+/// Published when a seed is issued, doesn't reveal the seed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SeedCommitment {
+    /// Identifies the game, chosen by the issuer
+    pub id: String,
+    pub width: usize,
+    pub height: usize,
+    /// When the seed was issued, as seconds since the unix epoch
+    pub issued_at: u64,
+    /// SHA-256 over the rest of the commitment and the [SeedOpening]
+    pub hash: String,
+}
+
+/// Kept secret by the issuer until the game is submitted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SeedOpening {
+    pub seed: u32,
+    /// Base64 encoded random bytes, keeps the seed from being guessed from the commitment
+    pub salt: String,
+}
+
+impl SeedCommitment {
+    /// Commit to a seed
+    pub fn new(
+        id: &str,
+        width: usize,
+        height: usize,
+        issued_at: u64,
+        opening: &SeedOpening,
+    ) -> Self {
+        let mut commitment = Self {
+            id: id.to_string(),
+            width,
+            height,
+            issued_at,
+            hash: String::new(),
+        };
+        commitment.hash = commitment.hash_with(opening);
+        commitment
+    }
+
+    /// Whether the opening is the one the commitment was made to
+    pub fn opens_with(&self, opening: &SeedOpening) -> bool {
+        self.hash_with(opening) == self.hash
+    }
+
+    fn hash_with(&self, opening: &SeedOpening) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(
+            format!(
+                "{}\0{}\0{}\0{}\0{}\0{}",
+                message_header(COMMITMENT_DOMAIN, &self.id),
+                self.width,
+                self.height,
+                self.issued_at,
+                opening.seed,
+                opening.salt
+            )
+            .as_bytes(),
+        );
+        format!("{:X}", hasher.finalize())
+    }
+}
+
+/// What the issuer stores for every seed it hands out, looked up by id when the game is submitted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct IssuedSeed {
+    pub commitment: SeedCommitment,
+    pub opening: SeedOpening,
+}
+
+/// Check that the recording was played from the committed seed and submitted at most `max_age` seconds after it was issued
+///
+/// The commitment must be the one the issuer stored, see [IssuedSeed], never one sent along with the submission.
+/// A player can commit to any seed and issue time themselves, so such a commitment proves nothing.
+pub fn verify(
+    recording: &SeededRecording,
+    commitment: &SeedCommitment,
+    opening: &SeedOpening,
+    submitted_at: u64,
+    max_age: u64,
+) -> Result<(), CommitmentError> {
+    if !commitment.opens_with(opening) {
+        return Err(CommitmentError::OpeningMismatch);
+    }
+    if recording.seed != opening.seed
+        || recording.width != commitment.width
+        || recording.height != commitment.height
+    {
+        return Err(CommitmentError::SeedMismatch);
+    }
+    let age = submitted_at.checked_sub(commitment.issued_at).ok_or(
+        CommitmentError::SubmittedBeforeIssue {
+            issued_at: commitment.issued_at,
+            submitted_at,
+        },
+    )?;
+    if age > max_age {
+        return Err(CommitmentError::Expired { age, max_age });
+    }
+    Ok(())
+}
+
+/// Issues seeds like a server would, without any of the storage or networking
+pub struct LocalIssuer<R: RngCore = StdRng> {
+    rng: R,
+    issued: usize,
+}
+
+impl LocalIssuer {
+    /// An issuer with reproducible seeds, for tests
+    pub fn from_seed(seed: u64) -> Self {
+        Self::new(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl<R: RngCore> LocalIssuer<R> {
+    pub fn new(rng: R) -> Self {
+        Self { rng, issued: 0 }
+    }
+
+    /// Pick a new seed, returning the commitment to publish and the opening to keep
+    pub fn issue(
+        &mut self,
+        width: usize,
+        height: usize,
+        now: u64,
+    ) -> (SeedCommitment, SeedOpening) {
+        let mut salt = [0; SALT_LENGTH];
+        self.rng.fill_bytes(&mut salt);
+        let opening = SeedOpening {
+            seed: self.rng.gen(),
+            salt: get_b64_engine().encode(salt),
+        };
+        let id = format!("local-{}", self.issued);
+        self.issued += 1;
+        (
+            SeedCommitment::new(&id, width, height, now, &opening),
+            opening,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::direction::Direction;
+
+    const ISSUED_AT: u64 = 1_700_000_000;
+    const HOUR: u64 = 60 * 60;
+
+    fn play(commitment: &SeedCommitment, opening: &SeedOpening) -> SeededRecording {
+        let mut recording =
+            SeededRecording::empty(opening.seed, commitment.width, commitment.height);
+        recording.moves = vec![Direction::UP, Direction::LEFT];
+        recording
+    }
+
+    #[test]
+    fn issue_and_verify() {
+        let mut issuer = LocalIssuer::from_seed(0);
+        let (commitment, opening) = issuer.issue(4, 4, ISSUED_AT);
+        let recording = play(&commitment, &opening);
+        assert_eq!(
+            verify(&recording, &commitment, &opening, ISSUED_AT + 60, HOUR),
+            Ok(())
+        );
+
+        let (other, _) = issuer.issue(4, 4, ISSUED_AT);
+        assert_ne!(other.id, commitment.id);
+        assert_ne!(other.hash, commitment.hash);
+    }
+
+    #[test]
+    fn rejects_other_seeds() {
+        let (commitment, opening) = LocalIssuer::from_seed(0).issue(4, 4, ISSUED_AT);
+        let mut recording = play(&commitment, &opening);
+        recording.seed = recording.seed.wrapping_add(1);
+        assert_eq!(
+            verify(&recording, &commitment, &opening, ISSUED_AT, HOUR),
+            Err(CommitmentError::SeedMismatch)
+        );
+
+        let forged = SeedOpening {
+            seed: recording.seed,
+            ..opening.clone()
+        };
+        assert_eq!(
+            verify(&recording, &commitment, &forged, ISSUED_AT, HOUR),
+            Err(CommitmentError::OpeningMismatch)
+        );
+
+        let mut backdated = commitment.clone();
+        backdated.issued_at -= HOUR;
+        assert!(!backdated.opens_with(&opening));
+    }
+
+    #[test]
+    fn time_window() {
+        let (commitment, opening) = LocalIssuer::from_seed(0).issue(4, 4, ISSUED_AT);
+        let recording = play(&commitment, &opening);
+        assert_eq!(
+            verify(&recording, &commitment, &opening, ISSUED_AT + HOUR, HOUR),
+            Ok(())
+        );
+        assert_eq!(
+            verify(
+                &recording,
+                &commitment,
+                &opening,
+                ISSUED_AT + HOUR + 1,
+                HOUR
+            ),
+            Err(CommitmentError::Expired {
+                age: HOUR + 1,
+                max_age: HOUR
+            })
+        );
+        assert_eq!(
+            verify(&recording, &commitment, &opening, ISSUED_AT - 1, HOUR),
+            Err(CommitmentError::SubmittedBeforeIssue {
+                issued_at: ISSUED_AT,
+                submitted_at: ISSUED_AT - 1
+            })
+        );
+    }
+}
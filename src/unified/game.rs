@@ -3,7 +3,11 @@ use serde::{Deserialize, Serialize};
 use crate::{
     board::{check_move, Board},
     direction::{self, Direction},
-    rules::RulesetProvider,
+    rules::{Ruleset, RulesetProvider},
+    v2::{
+        recording::SeededRecording,
+        replay::{MoveReplayError, ReplayState},
+    },
 };
 
 use super::reconstruction::Reconstructable;
@@ -29,10 +33,27 @@ impl GameState {
         let score_current = reconstruction.validation_data.score_end;
         let score_max = reconstruction.validation_data.score;
         let breaks = reconstruction.validation_data.breaks;
-        let board = *reconstruction.history.last().unwrap();
+        let board = reconstruction.history.last().unwrap().clone();
+        Ok(Self::from_board(
+            rules,
+            board,
+            score_current,
+            score_max,
+            breaks,
+        ))
+    }
+
+    /// Compute the allowed moves and the end conditions of a board
+    pub fn from_board(
+        rules: &dyn Ruleset,
+        board: Board,
+        score_current: usize,
+        score_max: usize,
+        breaks: usize,
+    ) -> Self {
         let mut allowed_moves = vec![];
         for direction in direction::MOVE_DIRECTIONS {
-            if check_move(board, direction).is_ok() {
+            if check_move(board.clone(), direction).is_ok() {
                 allowed_moves.push(direction);
             }
         }
@@ -43,7 +64,7 @@ impl GameState {
         }
 
         let won = rules.won(&board);
-        Ok(Self {
+        Self {
             board,
             score_max,
             score_current,
@@ -51,6 +72,206 @@ impl GameState {
             allowed_moves,
             over,
             won,
-        })
+        }
+    }
+}
+
+/// An interactive game session.
+///
+/// Moves are applied one at a time on top of the current state, and every state is kept around so that moves can be undone and redone.
+/// The [SeededRecording] of the moves played so far is available at any time through [Game::recording].
+#[derive(Debug, Clone)]
+pub struct Game {
+    recording: SeededRecording,
+    /// The state after each move, starting with the initial state
+    states: Vec<ReplayState>,
+    /// Undone moves along with the states they led to, the most recently undone last
+    undone: Vec<(Direction, ReplayState)>,
+}
+
+impl Game {
+    /// Start a new game
+    pub fn new(seed: u32, width: usize, height: usize) -> Self {
+        Self::from_recording(&SeededRecording::empty(seed, width, height))
+            .expect("an empty recording is always valid")
+    }
+
+    /// Continue a recorded game
+    pub fn from_recording(recording: &SeededRecording) -> Result<Self, MoveReplayError> {
+        let mut game = Self {
+            recording: SeededRecording {
+                moves: vec![],
+                // Timings can't be kept in sync with undone moves
+                timings: None,
+                ..recording.clone()
+            },
+            states: vec![ReplayState::new(recording)?],
+            undone: vec![],
+        };
+        for mv in recording.moves.iter().copied() {
+            game.apply(mv)?;
+        }
+        Ok(game)
+    }
+
+    /// Play a move. Any undone moves can't be redone afterwards.
+    pub fn play(&mut self, mv: Direction) -> Result<(), MoveReplayError> {
+        self.apply(mv)?;
+        self.undone.clear();
+        Ok(())
+    }
+
+    /// Undo the last move, returning it if there was one
+    pub fn undo(&mut self) -> Option<Direction> {
+        if self.states.len() < 2 {
+            return None;
+        }
+        let state = self.states.pop()?;
+        let mv = self.recording.moves.pop()?;
+        self.undone.push((mv, state));
+        Some(mv)
+    }
+
+    /// Redo the last undone move, returning it if there was one
+    pub fn redo(&mut self) -> Option<Direction> {
+        let (mv, state) = self.undone.pop()?;
+        self.recording.moves.push(mv);
+        self.states.push(state);
+        Some(mv)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.states.len() > 1
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// The recording of the moves played so far, excluding undone moves
+    pub fn recording(&self) -> &SeededRecording {
+        &self.recording
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.current().board
+    }
+
+    pub fn score(&self) -> usize {
+        self.current().score
+    }
+
+    pub fn score_max(&self) -> usize {
+        self.current().max_score
+    }
+
+    pub fn breaks(&self) -> usize {
+        self.current().breaks
+    }
+
+    /// Get the [GameState] of the current position
+    pub fn state(&self) -> GameState {
+        let state = self.current();
+        GameState::from_board(
+            self.recording.rules(),
+            state.board.clone(),
+            state.score,
+            state.max_score,
+            state.breaks,
+        )
+    }
+
+    fn current(&self) -> &ReplayState {
+        // There's always at least the initial state
+        self.states.last().unwrap()
+    }
+
+    fn apply(&mut self, mv: Direction) -> Result<(), MoveReplayError> {
+        let mut state = self.current().clone();
+        state.apply(self.recording.rules(), mv)?;
+        self.states.push(state);
+        self.recording.moves.push(mv);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        random::RandAlgo,
+        v2::{
+            metadata::Metadata,
+            test_data::{GAME_NI4FIRM, GAME_WON_3_BREAKS},
+        },
+    };
+
+    #[test]
+    fn matches_reconstruction() {
+        for data in [GAME_NI4FIRM, GAME_WON_3_BREAKS] {
+            let recording: SeededRecording = data.parse().unwrap();
+            let game = Game::from_recording(&recording).unwrap();
+            let expected = GameState::from_reconstructable_ruleset(&recording).unwrap();
+            let state = game.state();
+            assert_eq!(game.recording(), &recording);
+            assert_eq!(state.board.get_all_tiles(), expected.board.get_all_tiles());
+            assert_eq!(state.score_current, expected.score_current);
+            assert_eq!(state.score_max, expected.score_max);
+            assert_eq!(state.breaks, expected.breaks);
+            assert_eq!(state.allowed_moves, expected.allowed_moves);
+        }
+    }
+
+    #[test]
+    fn undo_redo() {
+        let recording: SeededRecording = GAME_WON_3_BREAKS.parse().unwrap();
+        let mut game = Game::from_recording(&recording).unwrap();
+        let last_board = game.board().clone();
+        assert!(!game.can_redo());
+
+        let mut undone = vec![];
+        while let Some(mv) = game.undo() {
+            undone.push(mv);
+        }
+        assert!(!game.can_undo());
+        assert_eq!(game.score(), 0);
+        assert!(game.recording().moves.is_empty());
+        assert_eq!(undone.len(), recording.moves.len());
+
+        while game.redo().is_some() {}
+        assert_eq!(game.recording(), &recording);
+        assert_eq!(game.board(), &last_board);
+    }
+
+    #[test]
+    fn play_clears_redo() {
+        let mut game = Game::new(0, 4, 4);
+        let mv = direction::MOVE_DIRECTIONS
+            .into_iter()
+            .find(|dir| game.play(*dir).is_ok())
+            .unwrap();
+        assert_eq!(game.undo(), Some(mv));
+        assert!(game.can_redo());
+        game.play(mv).unwrap();
+        assert!(!game.can_redo());
+        assert_eq!(game.recording().moves, vec![mv]);
+    }
+
+    #[test]
+    fn invalid_moves_are_rejected() {
+        let mut game = Game::new(0, 4, 4);
+        assert!(game.play(Direction::BREAK).is_err());
+        assert!(game.recording().moves.is_empty());
+        assert!(!game.can_undo());
+    }
+
+    #[test]
+    fn rejects_unknown_rng() {
+        let mut recording = SeededRecording::empty(0, 4, 4).with_rng(RandAlgo::Pcg32);
+        recording.metadata.set(Metadata::RNG, "mersenne");
+        assert!(matches!(
+            Game::from_recording(&recording),
+            Err(MoveReplayError::UnknownRng(_))
+        ));
     }
 }
@@ -1,3 +1,183 @@
+//! Provides [Hashable] for hashing the recording of a game and [PositionHashable] for hashing the game itself.
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    board::Board,
+    rules::{Ruleset, RulesetProvider},
+};
+
+use super::reconstruction::{HistoryReconstruction, Reconstructable};
+
 pub trait Hashable {
     fn game_hash(&self) -> String;
 }
+
+/// Hashes the positions a game goes through instead of how it was recorded.
+///
+/// The [game hashes](Hashable::game_hash) of different formats are never comparable, while the same game played out
+/// in any format gets the same position hash, which makes it usable for finding duplicates across formats.
+pub trait PositionHashable {
+    type PositionHashError;
+    fn position_hash(&self) -> Result<String, Self::PositionHashError>;
+}
+
+impl<T: Reconstructable + RulesetProvider> PositionHashable for T {
+    type PositionHashError = T::ReconstructionError;
+    fn position_hash(&self) -> Result<String, Self::PositionHashError> {
+        Ok(position_hash(&self.reconstruct()?, self.rules()))
+    }
+}
+
+/// Hash the board sequence of a reconstructed game along with the rules it was played with.
+///
+/// Only the values of the tiles are hashed, and a board equal to the one before it is skipped, so moves that don't
+/// change the board (like [END](crate::direction::Direction::END)) don't affect the hash.
+/// The rules are hashed by what they evaluate to on the starting board, so rulesets that behave the same on the
+/// size of the board give the same hash.
+pub fn position_hash(reconstruction: &HistoryReconstruction, rules: &dyn Ruleset) -> String {
+    let mut hasher = Sha256::new();
+    let mut previous: Option<Vec<usize>> = None;
+    for board in &reconstruction.history {
+        if previous.is_none() {
+            hasher.update(board.width.to_le_bytes());
+            hasher.update(board.height.to_le_bytes());
+            hash_rules(&mut hasher, rules, board);
+        }
+        let values = values(board);
+        if previous.as_ref() != Some(&values) {
+            for value in &values {
+                hasher.update(value.to_le_bytes());
+            }
+            previous = Some(values);
+        }
+    }
+    format!("P{:X}", hasher.finalize())
+}
+
+fn hash_rules(hasher: &mut Sha256, rules: &dyn Ruleset, board: &Board) {
+    for value in [
+        rules.break_cost(board),
+        rules.break_max(board),
+        rules.break_tile_threshold(board),
+        rules.initial_tiles(board),
+        rules.spawns_per_move(board),
+    ] {
+        hasher.update(value.to_le_bytes());
+    }
+    for weight in rules.spawn_weights(board) {
+        hasher.update(weight.value.to_le_bytes());
+        hasher.update(weight.weight.to_le_bytes());
+    }
+}
+
+/// The tile values of the board row by row, 0 for empty cells
+fn values(board: &Board) -> Vec<usize> {
+    board
+        .tiles
+        .iter()
+        .flatten()
+        .map(|tile| tile.map(|t| t.value).unwrap_or(0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::{
+            check_move,
+            tile::{InitialID, Tile},
+        },
+        direction::Direction,
+        unified::validation::Validatable,
+        v1::{parser::parse_data, recording::Recording, tests::lib_testgames},
+        v2::{
+            explicit::ExplicitRecording,
+            recording::SeededRecording,
+            test_data::{GAME_NI4FIRM, GAME_WON_3_BREAKS},
+        },
+    };
+
+    /// The moves of `recording` before its first break, recorded in the v1 format
+    fn as_v1(recording: &SeededRecording) -> Recording {
+        let history = recording.reconstruct().unwrap().history;
+        let mut steps = vec![];
+        for (index, &direction) in recording.moves.iter().enumerate() {
+            if direction == Direction::BREAK {
+                break;
+            }
+            let board = &history[index];
+            let predicted = values(&check_move(board.clone(), direction).unwrap().board);
+            let next = values(&history[index + 1]);
+            let addition = (0..next.len())
+                .find(|&i| predicted[i] != next[i])
+                .map(|i| Tile::new(i % board.width, i / board.width, next[i], InitialID::Id(0)));
+            steps.push((board.tiles.clone(), direction, addition));
+        }
+        steps.push((history[steps.len()].tiles.clone(), Direction::END, None));
+        let recording = Recording {
+            width: recording.width,
+            height: recording.height,
+            history: steps,
+        };
+        parse_data(&recording.to_string()).unwrap()
+    }
+
+    #[test]
+    fn same_game_across_formats() {
+        for data in [GAME_NI4FIRM, GAME_WON_3_BREAKS] {
+            let mut seeded: SeededRecording = data.parse().unwrap();
+            if let Some(first_break) = seeded.moves.iter().position(|&m| m == Direction::BREAK) {
+                seeded.moves.truncate(first_break);
+            }
+            let v1 = as_v1(&seeded);
+            assert!(v1.validate().is_ok());
+            assert_ne!(v1.game_hash(), seeded.game_hash());
+            assert_eq!(v1.position_hash().unwrap(), seeded.position_hash().unwrap());
+        }
+    }
+
+    #[test]
+    fn explicit_matches_v1() {
+        let v1 = parse_data(lib_testgames::GAME4X4).unwrap();
+        let explicit = ExplicitRecording::from(&v1);
+        assert_eq!(
+            v1.position_hash().unwrap(),
+            explicit.position_hash().unwrap()
+        );
+        assert_eq!(
+            crate::unified::position_hash(&String::from(&explicit)).unwrap(),
+            crate::unified::position_hash(lib_testgames::GAME4X4).unwrap()
+        );
+    }
+
+    #[test]
+    fn ignores_no_op_moves() {
+        let seeded: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        let mut padded = seeded.clone();
+        padded.moves.push(Direction::END);
+        assert_ne!(padded.game_hash(), seeded.game_hash());
+        assert_eq!(
+            padded.position_hash().unwrap(),
+            seeded.position_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn different_games() {
+        let seeded: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        let mut other = seeded.clone();
+        other.seed += 1;
+        other.moves.clear();
+        let mut shorter = seeded.clone();
+        shorter.moves.truncate(seeded.moves.len() / 2);
+        let hashes = [&seeded, &other, &shorter].map(|r| r.position_hash().unwrap());
+        assert_ne!(hashes[0], hashes[1]);
+        assert_ne!(hashes[0], hashes[2]);
+        assert_ne!(
+            crate::unified::position_hash(lib_testgames::GAME4X4).unwrap(),
+            crate::unified::position_hash(lib_testgames::GAME4X4B).unwrap()
+        );
+    }
+}
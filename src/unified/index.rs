@@ -0,0 +1,320 @@
+//! Finding games that are copies, prefixes or extensions of each other.
+//!
+//! [Hashable::game_hash](super::hash::Hashable::game_hash) only catches exact duplicates. A [GameIndex] stores the
+//! [GameKey] of every known game, grouped by where the game started from, and finds the known games that share moves with a new one.
+//! Games only share moves if they started the same way: v2 games from the same seed and size, v1 games from the same board.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::ParseResult;
+use crate::{
+    board::tile::{InitialID, Tile},
+    direction::Direction,
+    v1::recording::Recording,
+    v2::{explicit::ExplicitRecording, recording::SeededRecording},
+};
+
+/// Where a game started from
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Origin {
+    Seeded {
+        seed: u32,
+        width: usize,
+        height: usize,
+    },
+    /// The values of the initial board row by row, 0 for empty cells
+    Board {
+        width: usize,
+        height: usize,
+        values: Vec<usize>,
+    },
+}
+
+/// A single move of a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Step {
+    pub direction: Direction,
+    /// The tile added after the move, only recorded in v1 games
+    pub addition: Option<Tile>,
+}
+
+/// The parts of a recording that identify the game
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameKey {
+    pub origin: Origin,
+    pub steps: Vec<Step>,
+}
+
+impl From<&SeededRecording> for GameKey {
+    fn from(recording: &SeededRecording) -> Self {
+        Self {
+            origin: Origin::Seeded {
+                seed: recording.seed,
+                width: recording.width,
+                height: recording.height,
+            },
+            steps: recording
+                .moves
+                .iter()
+                .map(|&direction| Step {
+                    direction,
+                    addition: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&Recording> for GameKey {
+    fn from(recording: &Recording) -> Self {
+        let values = recording
+            .history
+            .first()
+            .map(|(tiles, _, _)| {
+                tiles
+                    .iter()
+                    .flatten()
+                    .map(|tile| tile.map(|t| t.value).unwrap_or(0))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            origin: Origin::Board {
+                width: recording.width,
+                height: recording.height,
+                values,
+            },
+            steps: recording
+                .history
+                .iter()
+                .map(|&(_, direction, addition)| Step {
+                    direction,
+                    addition,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The same key as the v1 recording it was converted from
+impl From<&ExplicitRecording> for GameKey {
+    fn from(recording: &ExplicitRecording) -> Self {
+        Self {
+            origin: Origin::Board {
+                width: recording.width,
+                height: recording.height,
+                values: match recording.steps.is_empty() {
+                    true => vec![],
+                    false => recording.start.clone(),
+                },
+            },
+            steps: recording
+                .steps
+                .iter()
+                .map(|step| Step {
+                    direction: step.direction,
+                    addition: step
+                        .spawn
+                        .map(|spawn| Tile::new(spawn.x, spawn.y, spawn.value, InitialID::Id(0))),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&ParseResult> for GameKey {
+    fn from(parsed: &ParseResult) -> Self {
+        match parsed {
+            ParseResult::V1(recording) => recording.into(),
+            ParseResult::V2(recording) => recording.into(),
+            ParseResult::Explicit(recording) => recording.into(),
+        }
+    }
+}
+
+/// How a game relates to a known one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Relation {
+    /// The games have the same moves
+    Duplicate,
+    /// The game is the start of the known game
+    PrefixOf,
+    /// The game continues the known game
+    ExtensionOf,
+    /// The games share some moves before going their own ways
+    Diverged,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexMatch {
+    /// The id the known game was inserted with
+    pub id: String,
+    pub relation: Relation,
+    /// How many moves the games have in common from the start
+    pub shared_moves: usize,
+}
+
+/// Stores known games for finding related ones
+#[derive(Debug, Clone, Default)]
+pub struct GameIndex {
+    games: HashMap<Origin, Vec<(String, Vec<Step>)>>,
+}
+
+impl GameIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a game under an id, e.g. the id of the submission
+    pub fn insert(&mut self, id: &str, key: GameKey) {
+        self.games
+            .entry(key.origin)
+            .or_default()
+            .push((id.to_string(), key.steps));
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Find the known games that started the same way and share at least `min_shared_moves` moves with the game.
+    ///
+    /// Duplicates, prefixes and extensions are always returned, even if they're shorter than `min_shared_moves`.
+    pub fn find(&self, key: &GameKey, min_shared_moves: usize) -> Vec<IndexMatch> {
+        let Some(games) = self.games.get(&key.origin) else {
+            return vec![];
+        };
+        games
+            .iter()
+            .filter_map(|(id, steps)| {
+                let shared_moves = key
+                    .steps
+                    .iter()
+                    .zip(steps)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let relation = match (shared_moves == key.steps.len(), shared_moves == steps.len())
+                {
+                    (true, true) => Relation::Duplicate,
+                    (true, false) => Relation::PrefixOf,
+                    (false, true) => Relation::ExtensionOf,
+                    (false, false) if shared_moves >= min_shared_moves => Relation::Diverged,
+                    (false, false) => return None,
+                };
+                Some(IndexMatch {
+                    id: id.clone(),
+                    relation,
+                    shared_moves,
+                })
+            })
+            .collect()
+    }
+
+    /// Whether the game is a duplicate, prefix or extension of a known game
+    pub fn contains_related(&self, key: &GameKey) -> bool {
+        // Diverged games would need to share more moves than the game has
+        !self.find(key, usize::MAX).is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::{parser::parse_data, tests::lib_testgames::GAME4X4};
+    use crate::v2::test_data::{GAME_NI4FIRM, GAME_WON_3_BREAKS};
+
+    fn seeded(data: &str) -> SeededRecording {
+        data.parse().unwrap()
+    }
+
+    #[test]
+    fn relations() {
+        let full = seeded(GAME_NI4FIRM);
+        let mut index = GameIndex::new();
+        index.insert("full", GameKey::from(&full));
+        index.insert("other", GameKey::from(&seeded(GAME_WON_3_BREAKS)));
+        assert_eq!(index.len(), 2);
+
+        let found = index.find(&GameKey::from(&full), 0);
+        assert_eq!(
+            found,
+            vec![IndexMatch {
+                id: "full".to_string(),
+                relation: Relation::Duplicate,
+                shared_moves: full.moves.len(),
+            }]
+        );
+
+        let mut prefix = full.clone();
+        prefix.moves.truncate(10);
+        assert_eq!(
+            index.find(&GameKey::from(&prefix), 0)[0].relation,
+            Relation::PrefixOf
+        );
+        assert!(index.contains_related(&GameKey::from(&prefix)));
+
+        let mut extension = full.clone();
+        extension.moves.push(Direction::UP);
+        assert_eq!(
+            index.find(&GameKey::from(&extension), 0)[0].relation,
+            Relation::ExtensionOf
+        );
+
+        let mut diverged = prefix.clone();
+        diverged.moves.push(match full.moves[10] {
+            Direction::UP => Direction::DOWN,
+            _ => Direction::UP,
+        });
+        assert_eq!(
+            index.find(&GameKey::from(&diverged), 10),
+            vec![IndexMatch {
+                id: "full".to_string(),
+                relation: Relation::Diverged,
+                shared_moves: 10,
+            }]
+        );
+        assert!(index.find(&GameKey::from(&diverged), 11).is_empty());
+        assert!(!index.contains_related(&GameKey::from(&diverged)));
+
+        let mut other_seed = full.clone();
+        other_seed.seed += 1;
+        assert!(index.find(&GameKey::from(&other_seed), 0).is_empty());
+    }
+
+    #[test]
+    fn v1() {
+        let recording = parse_data(GAME4X4).unwrap();
+        let mut index = GameIndex::new();
+        index.insert("v1", GameKey::from(&ParseResult::V1(recording.clone())));
+
+        let explicit = ExplicitRecording::from(&recording);
+        assert_eq!(
+            index.find(&GameKey::from(&explicit), 0)[0].relation,
+            Relation::Duplicate
+        );
+
+        let mut prefix = recording.clone();
+        prefix.history.truncate(5);
+        assert_eq!(
+            index.find(&GameKey::from(&prefix), 0)[0].relation,
+            Relation::PrefixOf
+        );
+
+        // The same moves with a different tile added aren't the same game
+        let mut altered = prefix.clone();
+        altered.history[2].2 = altered.history[2].2.map(|mut tile| {
+            tile.value *= 2;
+            tile
+        });
+        assert!(index.find(&GameKey::from(&altered), 3).is_empty());
+        let found = index.find(&GameKey::from(&altered), 2);
+        assert_eq!(found[0].relation, Relation::Diverged);
+        assert_eq!(found[0].shared_moves, 2);
+    }
+}
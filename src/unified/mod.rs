@@ -6,22 +6,33 @@ use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    direction::Direction,
     v1,
-    v2::{self, io::SEEDED_RECORDING_SEPARATOR, recording::SeededRecording},
+    v2::{
+        self, explicit::ExplicitRecording, io::SEEDED_RECORDING_SEPARATOR,
+        recording::SeededRecording,
+    },
+    version,
 };
 
 use self::{
-    hash::Hashable,
+    hash::{Hashable, PositionHashable},
     reconstruction::Reconstructable,
     validation::{Validatable, ValidationResult},
 };
 
+pub mod attestation;
+pub mod batch;
+pub mod commitment;
 pub mod game;
 pub mod hash;
+pub mod index;
 pub mod reconstruction;
+pub mod string_form;
 pub mod validation;
 
 const ERR_UNSUPPORTED_VERSION: &str = "unsupported protocol version";
+const ERR_NOT_SEEDED: &str = "only seeded recordings are supported";
 
 pub fn detect_version(data: &str) -> Option<u8> {
     let modern_prefix: String = format!(
@@ -37,47 +48,59 @@ pub fn detect_version(data: &str) -> Option<u8> {
                 .ok()
         });
     }
-    Some(1)
+    Some(version::V1)
 }
 
 pub fn validate(data: &str) -> anyhow::Result<ValidationResult> {
     match detect_version(data) {
-        Some(1) => {
+        Some(version::V1) => {
             let parsed = v1::parser::parse_data(data)?;
             Ok(parsed.validate()?)
         }
-        Some(2) => {
+        Some(version::SEEDED | version::SEEDED_METADATA) => {
             let parsed: v2::recording::SeededRecording = data.parse()?;
             Ok(parsed.validate()?)
         }
+        Some(version::EXPLICIT) => {
+            let parsed: ExplicitRecording = data.parse()?;
+            Ok(parsed.validate()?)
+        }
         _ => Err(anyhow!(ERR_UNSUPPORTED_VERSION)),
     }
 }
 
 pub fn reconstruct(data: &str) -> anyhow::Result<reconstruction::HistoryReconstruction> {
     match detect_version(data) {
-        Some(1) => {
+        Some(version::V1) => {
             let parsed = v1::parser::parse_data(data)?;
             Ok(parsed.reconstruct()?)
         }
-        Some(2) => {
+        Some(version::SEEDED | version::SEEDED_METADATA) => {
             let parsed: v2::recording::SeededRecording = data.parse()?;
             Ok(parsed.reconstruct()?)
         }
+        Some(version::EXPLICIT) => {
+            let parsed: ExplicitRecording = data.parse()?;
+            Ok(parsed.reconstruct()?)
+        }
         _ => Err(anyhow!(ERR_UNSUPPORTED_VERSION)),
     }
 }
 
 pub fn get_gamestate(data: &str) -> anyhow::Result<game::GameState> {
     match detect_version(data) {
-        Some(1) => {
+        Some(version::V1) => {
             let parsed = v1::parser::parse_data(data)?;
             Ok(game::GameState::from_reconstructable_ruleset(&parsed)?)
         }
-        Some(2) => {
+        Some(version::SEEDED | version::SEEDED_METADATA) => {
             let parsed: v2::recording::SeededRecording = data.parse()?;
             Ok(game::GameState::from_reconstructable_ruleset(&parsed)?)
         }
+        Some(version::EXPLICIT) => {
+            let parsed: ExplicitRecording = data.parse()?;
+            Ok(game::GameState::from_reconstructable_ruleset(&parsed)?)
+        }
         _ => Err(anyhow!(ERR_UNSUPPORTED_VERSION)),
     }
 }
@@ -88,20 +111,111 @@ pub fn get_gamestate(data: &str) -> anyhow::Result<game::GameState> {
 pub enum ParseResult {
     V1(v1::recording::Recording),
     V2(v2::recording::SeededRecording),
+    Explicit(v2::explicit::ExplicitRecording),
+}
+
+impl std::str::FromStr for ParseResult {
+    type Err = anyhow::Error;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        parse(data)
+    }
+}
+
+impl ParseResult {
+    /// The moves of the recording, the move at each index leads from the board at the same index of its
+    /// [reconstruction](reconstruction::Reconstructable) to the next one
+    pub fn moves(&self) -> Vec<Direction> {
+        match self {
+            ParseResult::V1(recording) => recording.history.iter().map(|step| step.1).collect(),
+            ParseResult::V2(recording) => recording.moves.clone(),
+            ParseResult::Explicit(recording) => {
+                recording.steps.iter().map(|step| step.direction).collect()
+            }
+        }
+    }
+}
+
+/// The text format of the recording's version
+impl From<&ParseResult> for String {
+    fn from(parsed: &ParseResult) -> Self {
+        match parsed {
+            ParseResult::V1(recording) => recording.to_string(),
+            ParseResult::V2(recording) => String::from(recording),
+            ParseResult::Explicit(recording) => String::from(recording),
+        }
+    }
 }
 
 pub fn parse(data: &str) -> anyhow::Result<ParseResult> {
     match detect_version(data) {
-        Some(1) => Ok(ParseResult::V1(v1::parser::parse_data(data)?)),
-        Some(2) => Ok(ParseResult::V2(data.parse()?)),
+        Some(version::V1) => Ok(ParseResult::V1(v1::parser::parse_data(data)?)),
+        Some(version::SEEDED | version::SEEDED_METADATA) => Ok(ParseResult::V2(data.parse()?)),
+        Some(version::EXPLICIT) => Ok(ParseResult::Explicit(data.parse()?)),
         _ => Err(anyhow!(ERR_UNSUPPORTED_VERSION)),
     }
 }
 
 pub fn hash(data: &str) -> anyhow::Result<String> {
     match detect_version(data) {
-        Some(1) => Ok(v1::parser::parse_data(data)?.game_hash()),
-        Some(2) => Ok(data.parse::<SeededRecording>()?.game_hash()),
+        Some(version::V1) => Ok(v1::parser::parse_data(data)?.game_hash()),
+        Some(version::SEEDED | version::SEEDED_METADATA) => {
+            Ok(data.parse::<SeededRecording>()?.game_hash())
+        }
+        Some(version::EXPLICIT) => Ok(data.parse::<ExplicitRecording>()?.game_hash()),
+        _ => Err(anyhow!(ERR_UNSUPPORTED_VERSION)),
+    }
+}
+
+/// The [position hash](hash::PositionHashable) of a recording of any version, equal for the same game in every version
+pub fn position_hash(data: &str) -> anyhow::Result<String> {
+    match detect_version(data) {
+        Some(version::V1) => Ok(v1::parser::parse_data(data)?.position_hash()?),
+        Some(version::SEEDED | version::SEEDED_METADATA) => {
+            Ok(data.parse::<SeededRecording>()?.position_hash()?)
+        }
+        Some(version::EXPLICIT) => Ok(data.parse::<ExplicitRecording>()?.position_hash()?),
+        _ => Err(anyhow!(ERR_UNSUPPORTED_VERSION)),
+    }
+}
+
+/// Check the [attestations](attestation) of a recording, see [attestation::verify]
+pub fn verify_attestation(
+    data: &str,
+    seed: &attestation::SeedAttestation,
+    submission: &attestation::SubmissionAttestation,
+    key: &attestation::VerifyingKey,
+) -> anyhow::Result<()> {
+    match detect_version(data) {
+        Some(version::V1 | version::EXPLICIT) => Err(anyhow!(ERR_NOT_SEEDED)),
+        Some(version::SEEDED | version::SEEDED_METADATA) => {
+            let parsed: SeededRecording = data.parse()?;
+            Ok(attestation::verify(&parsed, seed, submission, key)?)
+        }
+        _ => Err(anyhow!(ERR_UNSUPPORTED_VERSION)),
+    }
+}
+
+/// Check that a recording was played from a committed seed, see [commitment::verify] for where the commitment must come from
+pub fn verify_commitment(
+    data: &str,
+    commitment: &commitment::SeedCommitment,
+    opening: &commitment::SeedOpening,
+    submitted_at: u64,
+    max_age: u64,
+) -> anyhow::Result<()> {
+    match detect_version(data) {
+        Some(version::V1 | version::EXPLICIT) => Err(anyhow!(ERR_NOT_SEEDED)),
+        Some(version::SEEDED | version::SEEDED_METADATA) => {
+            let parsed: SeededRecording = data.parse()?;
+            Ok(commitment::verify(
+                &parsed,
+                commitment,
+                opening,
+                submitted_at,
+                max_age,
+            )?)
+        }
         _ => Err(anyhow!(ERR_UNSUPPORTED_VERSION)),
     }
 }
@@ -119,6 +233,72 @@ pub mod tests {
         assert_eq!(super::detect_version(data), Some(2));
     }
 
+    #[test]
+    fn detect_v3() {
+        let data = "::3:fakedatahere";
+        assert_eq!(super::detect_version(data), Some(3));
+    }
+
+    #[test]
+    fn parse_v3() {
+        use crate::v2::{metadata::Metadata, recording::SeededRecording};
+
+        let recording: SeededRecording = crate::v2::test_data::GAME_NI4FIRM.parse().unwrap();
+        let mut metadata = Metadata::default();
+        metadata.set(Metadata::CLIENT_BUILD, "1.0.0");
+        let data = String::from(&recording.with_metadata(metadata));
+        assert!(super::validate(&data).is_ok());
+        assert!(super::hash(&data).unwrap().starts_with("V3"));
+        let Ok(super::ParseResult::V2(parsed)) = super::parse(&data) else {
+            panic!("expected a seeded recording");
+        };
+        assert_eq!(parsed.metadata.client_build(), Some("1.0.0"));
+    }
+
+    #[test]
+    fn attestation() {
+        use super::attestation::{SeedAttestation, SigningKey, SubmissionAttestation};
+        use crate::v2::recording::SeededRecording;
+
+        let key = SigningKey::Ed25519([1; 32]);
+        let data = crate::v2::test_data::GAME_NI4FIRM;
+        let recording: SeededRecording = data.parse().unwrap();
+        let seed = SeedAttestation::sign(&key, "a", recording.seed, 4, 4).unwrap();
+        let submission = SubmissionAttestation::sign(&key, "a", &recording).unwrap();
+        assert!(super::verify_attestation(data, &seed, &submission, &key.verifying_key()).is_ok());
+        assert!(super::verify_attestation(
+            crate::v1::tests::lib_testgames::GAME4X4,
+            &seed,
+            &submission,
+            &key.verifying_key()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn explicit() {
+        use crate::v2::explicit::ExplicitRecording;
+
+        let v1 = crate::v1::tests::lib_testgames::GAME4X4;
+        let recording = crate::v1::parser::parse_data(v1).unwrap();
+        let data = String::from(&ExplicitRecording::from(&recording));
+        assert_eq!(super::detect_version(&data), Some(4));
+        assert_eq!(
+            super::validate(&data).unwrap(),
+            super::validate(v1).unwrap()
+        );
+        assert_eq!(super::hash(&data).unwrap(), super::hash(v1).unwrap());
+        assert!(matches!(
+            super::parse(&data),
+            Ok(super::ParseResult::Explicit(_))
+        ));
+        assert!(super::get_gamestate(&data).is_ok());
+        assert_eq!(
+            super::parse(&data).unwrap().moves(),
+            super::parse(v1).unwrap().moves()
+        );
+    }
+
     #[test]
     fn validate_v1() {
         let data = crate::v1::tests::lib_testgames::GAME4X4;
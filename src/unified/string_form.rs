@@ -0,0 +1,70 @@
+//! Serde adapter for serializing a [ParseResult] as the text format of its version instead of a tagged struct.
+//!
+//! Works like the [adapter for seeded recordings](crate::v2::string_form), use it with
+//! `#[serde(with = "twothousand_forty_eight::unified::string_form")]`.
+//! Both the text format of any version and the tagged struct form are accepted on input.
+
+use std::marker::PhantomData;
+
+use serde::{Deserializer, Serializer};
+
+use super::ParseResult;
+use crate::v2::string_form::StringOrStruct;
+
+pub fn serialize<S: Serializer>(parsed: &ParseResult, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&String::from(parsed))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ParseResult, D::Error> {
+    deserializer.deserialize_any(StringOrStruct(PhantomData))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        v1::tests::lib_testgames::GAME3X3,
+        v2::{explicit::ExplicitRecording, test_data::GAME_NI4FIRM},
+    };
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Wrapper {
+        #[serde(with = "super")]
+        recording: ParseResult,
+    }
+
+    #[test]
+    fn every_version() {
+        let v1 = crate::v1::parser::parse_data(GAME3X3).unwrap();
+        let explicit = String::from(&ExplicitRecording::from(&v1));
+        for data in [
+            GAME3X3.trim(),
+            GAME_NI4FIRM.lines().next().unwrap(),
+            &explicit,
+        ] {
+            let wrapper: Wrapper =
+                serde_json::from_value(serde_json::json!({ "recording": data })).unwrap();
+            let json = serde_json::to_value(&wrapper).unwrap();
+            let reparsed: Wrapper = serde_json::from_value(json.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&reparsed).unwrap(), json);
+            assert_eq!(
+                crate::unified::hash(json["recording"].as_str().unwrap()).unwrap(),
+                crate::unified::hash(data).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn accepts_struct_form() {
+        let parsed = crate::unified::parse(GAME_NI4FIRM).unwrap();
+        let json = serde_json::json!({ "recording": parsed });
+        let wrapper: Wrapper = serde_json::from_value(json).unwrap();
+        assert!(matches!(wrapper.recording, ParseResult::V2(_)));
+        assert_eq!(
+            serde_json::to_value(&wrapper).unwrap()["recording"],
+            GAME_NI4FIRM.lines().next().unwrap()
+        );
+    }
+}
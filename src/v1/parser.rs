@@ -29,6 +29,8 @@ pub enum ParseError {
 
     #[error("missing tile value on move {0}")]
     InvalidTileValue(usize),
+    #[error("expected {1} tile values on move {0}, got {2}")]
+    InvalidTileCount(usize, usize, usize),
 
     #[error("missing x position for addition on move {0}")]
     MissingAddX(usize),
@@ -50,7 +52,7 @@ pub enum ParseError {
 ///
 /// The string should be in the following format:
 /// - The string can start with ```[w]x[h]S```, specifying the size of the game board, otherwise it defaults to 4 by 4.
-/// (w = width, h = height, both are a [usize])
+///   (w = width, h = height, both are a [usize])
 /// - History indicies should be separated by a single ```:```
 ///     - ```;``` separates the history index to:
 ///         - The board data of the move on the left side, separated by a ```+```:
@@ -91,17 +93,26 @@ pub fn parse_data(data: &str) -> Result<Recording, ParseError> {
         let b = *bdata
             .first()
             .ok_or(ParseError::MissingBoard(history_index))?;
+        let values = b.split('.').collect::<Vec<&str>>();
+        let cells = width.checked_mul(height);
+        if cells != Some(values.len()) {
+            return Err(ParseError::InvalidTileCount(
+                history_index,
+                cells.unwrap_or(usize::MAX),
+                values.len(),
+            ));
+        }
         let mut tiles = initialize_tiles(width, height, &mut id_counter);
         let dir = parts
             .get(1)
             .ok_or(ParseError::MissingDirection(history_index))?;
         let direction = Direction::from_index_str(dir);
-        for (index, value) in b.split('.').enumerate() {
+        for (index, value) in values.into_iter().enumerate() {
             let val = value
                 .parse::<usize>()
                 .map_err(|_| ParseError::InvalidTileValue(history_index))?;
             let x = index % width;
-            let y = index / height;
+            let y = index / width;
             tiles[y][x] = Some(board::tile::Tile::new(
                 x,
                 y,
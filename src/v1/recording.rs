@@ -41,33 +41,27 @@ impl Hashable for Recording {
 }
 
 impl std::fmt::Display for Recording {
-    /// Converts the recording back to a format the [parser](crate::v1::parser) can read.
+    /// Converts the recording back to a format the [parser](crate::v1::parser) can read, always including the size of the board.
+    ///
+    /// Parsing the output gives back an equal recording, as long as the recording could have been parsed in the first place:
+    /// the format has no way to represent missing tiles, [Direction::START] or an empty history.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut out = "".to_owned();
-        for (index, i) in self.clone().history.into_iter().enumerate() {
-            let board = crate::board::Board::from((i.0, 0));
-            let tiles = board.get_all_tiles();
-            out += tiles
-                .iter()
-                .map(|t| t.value.to_string())
-                .collect::<Vec<String>>()
-                .join(".")
-                .as_str();
-            out += "+";
-            if let Some(t) = i.2 {
-                out = out
-                    + t.x.to_string().as_str()
-                    + ","
-                    + t.y.to_string().as_str()
-                    + "."
-                    + t.value.to_string().as_str()
+        write!(f, "{}x{}S", self.width, self.height)?;
+        for (index, (tiles, direction, addition)) in self.history.iter().enumerate() {
+            if index > 0 {
+                write!(f, ":")?;
             }
-            out += ";";
-            out += i.1.get_shorthand();
-            if index < self.history.len() - 1 {
-                out += ":";
+            let values = tiles
+                .iter()
+                .flatten()
+                .map(|tile| tile.map(|t| t.value).unwrap_or(0).to_string())
+                .collect::<Vec<String>>();
+            write!(f, "{}+", values.join("."))?;
+            if let Some(t) = addition {
+                write!(f, "{},{}.{}", t.x, t.y, t.value)?;
             }
+            write!(f, ";{}", direction.get_shorthand())?;
         }
-        write!(f, "{}", out)
+        Ok(())
     }
 }
@@ -6,8 +6,8 @@ mod board {
     use board::Board;
     #[test]
     fn creation_works() {
-        for w in 0..board::MAX_WIDTH {
-            for h in 0..board::MAX_HEIGHT {
+        for w in 0..8 {
+            for h in 0..8 {
                 let mut board = Board::new(w, h, 1);
 
                 let mut index = 0;
@@ -114,6 +114,21 @@ mod parser {
         assert_eq!(history.history.len(), 500);
     }
 
+    #[test]
+    fn rejects_wrong_tile_count() {
+        for data in [
+            "4x4S0.0.0.0.0.0.0.0.0.0.0.0.0.0.2.2.2;1",
+            "4x4S0.0.0.0.0.0.0.0.0.0.0.0.0.2.2;1",
+            "0x0S0;1",
+        ] {
+            assert!(matches!(
+                parser::parse_data(data),
+                Err(parser::ParseError::InvalidTileCount(0, ..))
+            ));
+        }
+        assert!(crate::unified::parse("4x4S0.0.0.0.0.0.0.0.0.0.0.0.0.0.2.2.2;1").is_err());
+    }
+
     #[test]
     fn produces_coherrent_ids_a() {
         use lib_testgames::GAME4X4;
@@ -188,13 +203,13 @@ mod validator {
 
         for (i, item) in history.iter().enumerate() {
             println!("history index {}", i);
-            let history_tiles = item.0;
-            let history_board = Board::from((history_tiles, rng_state));
+            let history_tiles = &item.0;
+            let history_board = Board::from((history_tiles.clone(), rng_state));
             println!("recorded board");
-            println!("{}", Board::from((history_tiles, 0)));
-            let rec_board = reconstruction.history[i];
+            println!("{}", Board::from((history_tiles.clone(), 0)));
+            let rec_board = &reconstruction.history[i];
             println!("predicted board");
-            println!("{}", Board::from((rec_board.tiles, 0)));
+            println!("{}", Board::from((rec_board.tiles.clone(), 0)));
 
             let t1 = history_board.get_all_tiles();
             let t2 = rec_board.get_all_tiles();
@@ -221,6 +236,49 @@ mod validator {
         assert_score(result.score, 2788, result.score_margin);
         assert_eq!(result.breaks, 0);
     }
+    #[test]
+    fn reports_invalid_move() {
+        use lib_testgames::GAME4X4B;
+        let mut history = parser::parse_data(GAME4X4B).unwrap();
+        let tile = history.history[5]
+            .0
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .next()
+            .unwrap();
+        tile.value *= 2;
+        let (x, y, value) = (tile.x, tile.y, tile.value);
+
+        let Err(validator::ValidationError::InvalidScore(diagnostic)) = history.validate() else {
+            panic!("the altered board should be rejected");
+        };
+        assert_eq!(diagnostic.index, 4);
+        assert_eq!(diagnostic.direction, history.history[4].1);
+        assert_eq!(
+            diagnostic.recorded_score,
+            diagnostic.predicted_score + value / 2
+        );
+        assert_eq!(
+            diagnostic.diff,
+            vec![validator::CellDiff {
+                x,
+                y,
+                predicted: value / 2,
+                recorded: value,
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_addition_outside_board() {
+        let history = parser::parse_data("2x2S2.0.0.0+5,0.2;1:0.2.0.2;0:0.2.0.2;0").unwrap();
+        assert!(matches!(
+            history.validate(),
+            Err(validator::ValidationError::InvalidAddition(0, _))
+        ));
+    }
+
     #[test]
     fn works_normal_4x4_0breaks_b() {
         use lib_testgames::GAME4X4C;
@@ -308,6 +366,54 @@ mod serializers {
     }
 }
 
+#[cfg(test)]
+mod recording_serializer {
+    use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+
+    use super::lib_testgames;
+    use crate::{unified::hash::Hashable, v1::parser};
+
+    fn assert_round_trip(data: &str) {
+        let recording = parser::parse_data(data).unwrap();
+        let serialized = recording.to_string();
+        let parsed = parser::parse_data(&serialized).unwrap();
+        assert_eq!(parsed, recording);
+        assert_eq!(parsed.game_hash(), recording.game_hash());
+        assert_eq!(parsed.to_string(), serialized);
+    }
+
+    #[test]
+    fn round_trip() {
+        for data in [
+            lib_testgames::GAME3X3,
+            lib_testgames::GAME3X3B,
+            lib_testgames::GAME4X4,
+            lib_testgames::GAME4X4B,
+            lib_testgames::GAME4X4C,
+            lib_testgames::GAMEOBSCENE,
+        ] {
+            assert_round_trip(data);
+        }
+    }
+
+    #[test]
+    fn includes_size() {
+        let recording = parser::parse_data(lib_testgames::GAME3X3).unwrap();
+        assert!(recording.to_string().starts_with("3x3S"));
+        // Games without the size prefix are 4x4
+        let recording = parser::parse_data(lib_testgames::GAMEOBSCENE).unwrap();
+        assert!(recording.to_string().starts_with("4x4S"));
+    }
+
+    #[test]
+    #[ignore = "slow"]
+    fn round_trip_all_real() {
+        use lib_testgames::GAMELIST;
+        let games: Vec<&str> = GAMELIST.lines().collect();
+        games.par_iter().for_each(|game| assert_round_trip(game));
+    }
+}
+
 #[cfg(test)]
 mod history_hash {
     use super::lib_testgames;
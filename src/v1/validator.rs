@@ -22,8 +22,78 @@ pub enum ValidationError {
     #[error("invalid addition `{1:?}` on move {0}")]
     InvalidAddition(usize, Tile),
 
-    #[error("invalid score on move {0}, expected `{1}` but got `{2}`")]
-    InvalidScore(usize, usize, usize),
+    #[error(
+        "invalid score on move {}, expected `{}` but got `{}`",
+        .0.index,
+        .0.predicted_score,
+        .0.recorded_score
+    )]
+    InvalidScore(Box<MoveDiagnostic>),
+}
+
+/// A cell whose predicted value doesn't match the recording, 0 meaning an empty cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct CellDiff {
+    pub x: usize,
+    pub y: usize,
+    pub predicted: usize,
+    pub recorded: usize,
+}
+
+/// Describes a move that doesn't lead to the recorded board
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct MoveDiagnostic {
+    /// Index of the move in the history
+    pub index: usize,
+    pub direction: Direction,
+    /// The tile the recording says was added after the move
+    pub addition: Option<Tile>,
+    /// The board the move was made on
+    pub previous: Board,
+    /// The board the move should have led to
+    pub predicted: Board,
+    /// The board the recording has instead
+    pub recorded: Board,
+    /// Sum of the tiles on the predicted board
+    pub predicted_score: usize,
+    /// Sum of the tiles on the recorded board
+    pub recorded_score: usize,
+    /// The cells that differ, row by row
+    pub diff: Vec<CellDiff>,
+}
+
+impl MoveDiagnostic {
+    fn diff(predicted: &Board, recorded: &Board) -> Vec<CellDiff> {
+        let value = |board: &Board, x: usize, y: usize| {
+            board
+                .tiles
+                .get(y)
+                .and_then(|row| row.get(x))
+                .copied()
+                .flatten()
+                .map(|tile| tile.value)
+                .unwrap_or(0)
+        };
+        let mut diff = vec![];
+        for y in 0..predicted.height.max(recorded.height) {
+            for x in 0..predicted.width.max(recorded.width) {
+                let (predicted, recorded) = (value(predicted, x, y), value(recorded, x, y));
+                if predicted != recorded {
+                    diff.push(CellDiff {
+                        x,
+                        y,
+                        predicted,
+                        recorded,
+                    });
+                }
+            }
+        }
+        diff
+    }
 }
 
 impl Validatable for Recording {
@@ -49,7 +119,7 @@ impl Reconstructable for Recording {
         let mut history_out: Vec<Board> = vec![];
         if history_len > 0 {
             // Push the starting position into history, as it is not validated here.
-            let board = Board::from((self.history[0].0, rng_state));
+            let board = Board::from((self.history[0].0.clone(), rng_state));
             rng_state = board.rng_state; // Update the rng state
             history_out.push(board);
         }
@@ -57,19 +127,19 @@ impl Reconstructable for Recording {
         let mut breaks: usize = 0;
         let mut break_positions = [None; MAX_ALLOWED_BREAKS];
         for ind in 0..history_len {
-            let i = self.history[ind];
+            let i = &self.history[ind];
 
-            let board = i.0;
+            let board = &i.0;
             let dir = i.1;
             let addition = self.history[ind].2;
 
             let tiles = if ind > 0 {
-                history_out[ind].tiles
+                history_out[ind].tiles.clone()
             } else {
-                board
+                board.clone()
             };
-            let board_to_check = Board::from((tiles, rng_state));
-            let predicted = check_move(board_to_check, dir);
+            let board_to_check = Board::from((tiles.clone(), rng_state));
+            let predicted = check_move(board_to_check.clone(), dir);
             let mut predicted_board = tiles;
             if let Ok(data) = predicted {
                 predicted_board = data.board.tiles;
@@ -78,22 +148,28 @@ impl Reconstructable for Recording {
             }
 
             if ind < (history_len - 1) && ind < MAX_HISTORY_LENGTH {
-                let board_next = self.history[ind + 1].0;
+                let board_next = &self.history[ind + 1].0;
                 if let Some(add) = addition {
                     if add.value > 4 {
-                        println!("Invalid addition value at {:?}!", add);
                         return Err(ValidationError::InvalidAddition(ind, add));
                     };
-                    predicted_board[add.y][add.x] = Some(add);
+                    let Some(cell) = predicted_board
+                        .get_mut(add.y)
+                        .and_then(|row| row.get_mut(add.x))
+                    else {
+                        return Err(ValidationError::InvalidAddition(ind, add));
+                    };
+                    *cell = Some(add);
                 }
 
-                let mut board_predicted = Board::from((predicted_board, board_to_check.rng_state));
-                let board_actual = Board::from((board_next, board_to_check.rng_state));
+                let mut board_predicted =
+                    Board::from((predicted_board.clone(), board_to_check.rng_state));
+                let board_actual = Board::from((board_next.clone(), board_to_check.rng_state));
                 let expected_score = board_predicted.get_total_value();
                 let actual_score = board_actual.get_total_value();
 
                 let ended_correctly = dir == Direction::END && expected_score == actual_score;
-                let continued_correctly = predicted_board == board_next;
+                let continued_correctly = &predicted_board == board_next;
                 if ended_correctly || continued_correctly {
                     // Do nothing, the move was valid
                 } else if (expected_score > actual_score)
@@ -105,39 +181,26 @@ impl Reconstructable for Recording {
                     score -= 1000;
 
                     // Clear tiles with value < 16
-                    board_predicted.tiles = board_predicted.tiles.map(|c| {
-                        c.map(|t| {
-                            t.map(|t| {
-                                if t.value >= 16 {
-                                    t
-                                } else {
-                                    Tile { value: 0, ..t }
-                                }
-                            })
-                        })
-                    });
+                    for t in board_predicted.tiles.iter_mut().flatten().flatten() {
+                        if t.value < 16 {
+                            t.value = 0;
+                        }
+                    }
                 } else {
                     // Invalid move
-                    println!(
-                        "Went wrong at index {} (move to {:?}, add {:?}): \n{:?}\n{:?}",
-                        ind, dir, addition, predicted_board, board_next
-                    );
-                    if ind > 0 {
-                        println!("Last board:");
-                        println!(
-                            "{}",
-                            Board::from((history_out.last().unwrap().tiles, rng_state))
-                        );
-                    }
-                    println!("Expected: (score {}) ", expected_score);
-                    println!("{}", Board::from((predicted_board, rng_state)));
-                    println!("Got instead: (score {}) ", actual_score);
-                    println!("{}", Board::from((board_next, rng_state)));
-                    return Err(ValidationError::InvalidScore(
-                        ind,
-                        expected_score,
-                        actual_score,
-                    ));
+                    let predicted = Board::from((predicted_board, rng_state));
+                    let recorded = Board::from((board_next.clone(), rng_state));
+                    return Err(ValidationError::InvalidScore(Box::new(MoveDiagnostic {
+                        index: ind,
+                        direction: dir,
+                        addition,
+                        previous: board_to_check,
+                        diff: MoveDiagnostic::diff(&predicted, &recorded),
+                        predicted,
+                        recorded,
+                        predicted_score: expected_score,
+                        recorded_score: actual_score,
+                    })));
                 }
                 history_out.push(board_predicted);
             }
@@ -148,9 +211,9 @@ impl Reconstructable for Recording {
         match last_history {
             None => {}
             Some(last_history) => {
-                let last_board = last_history.0;
+                let last_board = &last_history.0;
                 for dir in direction::REAL_DIRECTIONS {
-                    let board = Board::from((last_board, rng_state));
+                    let board = Board::from((last_board.clone(), rng_state));
                     rng_state = board.rng_state;
                     let predicted = check_move(board, dir);
                     if let Ok(data) = predicted {
@@ -183,7 +246,7 @@ impl RulesetProvider for Recording {
 pub fn validate_first_move(history: &Recording) -> bool {
     let history_len = history.history.len();
     if history_len > 0 {
-        let first_frame = history.history[0].0;
+        let first_frame = history.history[0].0.clone();
         let first_board = Board::from((first_frame, 0));
         for tile in first_board.get_all_tiles() {
             if tile.value > 4 {
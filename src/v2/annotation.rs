@@ -0,0 +1,275 @@
+//! Provides [Annotations], the commentary carried on the lines after the first line of a [SeededRecording](super::recording::SeededRecording).
+//!
+//! Each line is a single [Annotation]:
+//!
+//! - `@note (move) (text)`: a note on the move with the index, counting from 0
+//! - `@bookmark (move) [label]`: marks a move worth jumping to
+//! - `@comment (text)`: a comment on the whole game
+//! - `@author (name)`: who wrote the annotations
+//!
+//! Newlines, carriage returns and backslashes in the text are escaped as `\n`, `\r` and `\\`.
+//! Any other line, like the stats the TUI example writes, is kept as [Text](Annotation::Text), so free-form comments
+//! keep working. This includes lines that start with a keyword but don't parse, e.g. `@note` without a move.
+//! Text that wouldn't be read back the same way as is, e.g. text with a newline, is written as `@text (text)`.
+//! Blank lines are skipped.
+
+use serde::{Deserialize, Serialize};
+
+const NOTE: &str = "@note";
+const BOOKMARK: &str = "@bookmark";
+const COMMENT: &str = "@comment";
+const AUTHOR: &str = "@author";
+const TEXT: &str = "@text";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum Annotation {
+    /// A note on the move with the index
+    Note { index: usize, text: String },
+    /// A move worth jumping to
+    Bookmark { index: usize, label: Option<String> },
+    /// A comment on the whole game
+    Comment(String),
+    /// Who wrote the annotations
+    Author(String),
+    /// A line that isn't any of the above, written back as is when possible
+    Text(String),
+}
+
+/// The annotations of a recording, in the order they were written
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(transparent)]
+pub struct Annotations(pub Vec<Annotation>);
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn push(&mut self, annotation: Annotation) {
+        self.0.push(annotation);
+    }
+
+    /// The notes on the move with the index
+    pub fn notes(&self, index: usize) -> impl Iterator<Item = &str> {
+        self.0
+            .iter()
+            .filter_map(move |annotation| match annotation {
+                Annotation::Note { index: i, text } if *i == index => Some(text.as_str()),
+                _ => None,
+            })
+    }
+
+    /// The indices of the bookmarked moves along with their labels
+    pub fn bookmarks(&self) -> impl Iterator<Item = (usize, Option<&str>)> {
+        self.0.iter().filter_map(|annotation| match annotation {
+            Annotation::Bookmark { index, label } => Some((*index, label.as_deref())),
+            _ => None,
+        })
+    }
+
+    pub fn comments(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().filter_map(|annotation| match annotation {
+            Annotation::Comment(text) => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The last author given
+    pub fn author(&self) -> Option<&str> {
+        self.0.iter().rev().find_map(|annotation| match annotation {
+            Annotation::Author(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The lines of the text format, one for each annotation
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.0.iter().map(|annotation| match annotation {
+            Annotation::Note { index, text } => format!("{NOTE} {index} {}", escape(text)),
+            Annotation::Bookmark { index, label: None } => format!("{BOOKMARK} {index}"),
+            Annotation::Bookmark {
+                index,
+                label: Some(label),
+            } => format!("{BOOKMARK} {index} {}", escape(label)),
+            Annotation::Comment(text) => format!("{COMMENT} {}", escape(text)),
+            Annotation::Author(name) => format!("{AUTHOR} {}", escape(name)),
+            Annotation::Text(text) if reads_back_as_text(text) => text.clone(),
+            Annotation::Text(text) => format!("{TEXT} {}", escape(text)),
+        })
+    }
+
+    /// Parse the lines written by [lines](Self::lines)
+    pub fn from_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        Self(
+            lines
+                .into_iter()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| parse_line(line).unwrap_or_else(|| Annotation::Text(line.to_string())))
+                .collect(),
+        )
+    }
+}
+
+/// Parse a line starting with a keyword, None if it doesn't
+fn parse_line(line: &str) -> Option<Annotation> {
+    let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+    Some(match keyword {
+        NOTE => {
+            let (index, text) = split_index(rest)?;
+            Annotation::Note {
+                index,
+                text: unescape(text.unwrap_or_default())?,
+            }
+        }
+        BOOKMARK => {
+            let (index, label) = split_index(rest)?;
+            Annotation::Bookmark {
+                index,
+                label: match label {
+                    Some(label) => Some(unescape(label)?),
+                    None => None,
+                },
+            }
+        }
+        COMMENT => Annotation::Comment(unescape(rest)?),
+        AUTHOR => Annotation::Author(unescape(rest)?),
+        TEXT => Annotation::Text(unescape(rest)?),
+        _ => return None,
+    })
+}
+
+/// True if `text` written as is would be read back as the same [Annotation::Text]
+fn reads_back_as_text(text: &str) -> bool {
+    !text.trim().is_empty() && !text.contains(['\n', '\r']) && parse_line(text).is_none()
+}
+
+/// Split the move index from the start of `rest`, returning the text after it if there is any
+fn split_index(rest: &str) -> Option<(usize, Option<&str>)> {
+    let (index, text) = match rest.split_once(' ') {
+        Some((index, text)) => (index, Some(text)),
+        None => (rest, None),
+    };
+    Some((index.parse().ok()?, text))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                '\\' => out.push('\\'),
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                _ => return None,
+            },
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotations() -> Annotations {
+        Annotations(vec![
+            Annotation::Author("player 1".to_string()),
+            Annotation::Note {
+                index: 3,
+                text: "a risky move\\\nbut it worked".to_string(),
+            },
+            Annotation::Bookmark {
+                index: 10,
+                label: None,
+            },
+            Annotation::Bookmark {
+                index: 20,
+                label: Some("the first 512".to_string()),
+            },
+            Annotation::Comment("".to_string()),
+            Annotation::Text("Score: 604".to_string()),
+        ])
+    }
+
+    #[test]
+    fn round_trip() {
+        let annotations = annotations();
+        let lines: Vec<String> = annotations.lines().collect();
+        assert_eq!(lines[1], "@note 3 a risky move\\\\\\nbut it worked");
+        assert_eq!(lines[2], "@bookmark 10");
+        assert_eq!(
+            Annotations::from_lines(lines.iter().map(String::as_str)),
+            annotations
+        );
+    }
+
+    #[test]
+    fn accessors() {
+        let annotations = annotations();
+        assert_eq!(annotations.author(), Some("player 1"));
+        assert_eq!(
+            annotations.notes(3).collect::<Vec<_>>(),
+            vec!["a risky move\\\nbut it worked"]
+        );
+        assert_eq!(annotations.notes(4).count(), 0);
+        assert_eq!(
+            annotations.bookmarks().collect::<Vec<_>>(),
+            vec![(10, None), (20, Some("the first 512"))]
+        );
+        assert_eq!(annotations.comments().collect::<Vec<_>>(), vec![""]);
+    }
+
+    #[test]
+    fn free_form() {
+        let parsed = Annotations::from_lines(["------- STATS -------", "", "@unknown 1", "@notes"]);
+        assert_eq!(
+            parsed.0,
+            vec![
+                Annotation::Text("------- STATS -------".to_string()),
+                Annotation::Text("@unknown 1".to_string()),
+                Annotation::Text("@notes".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_text() {
+        let lines = ["@note", "@bookmark first", "@comment \\t"];
+        assert_eq!(
+            Annotations::from_lines(lines).0,
+            lines.map(|line| Annotation::Text(line.to_string()))
+        );
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let annotations = Annotations(vec![
+            Annotation::Text("Score: 604\nMoves: 300".to_string()),
+            Annotation::Text("@note 3 not a note".to_string()),
+            Annotation::Text("@text".to_string()),
+            Annotation::Text(" ".to_string()),
+            Annotation::Text("@note".to_string()),
+        ]);
+        let lines: Vec<String> = annotations.lines().collect();
+        assert_eq!(lines[0], "@text Score: 604\\nMoves: 300");
+        assert_eq!(lines[1], "@text @note 3 not a note");
+        assert_eq!(lines[4], "@note");
+        assert!(lines.iter().all(|line| !line.contains('\n')));
+        assert_eq!(
+            Annotations::from_lines(lines.iter().map(String::as_str)),
+            annotations
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! A compact binary encoding of [SeededRecording], for storing recordings as bytes.
+//!
+//! Unlike the text format, the moves are packed without a base conversion, so encoding and decoding are linear in the
+//! length of the game. Everything the text format carries survives the round trip, so a recording can be converted
+//! between the two freely and keeps its [game hash](crate::unified::hash::Hashable::game_hash).
+//!
+//! Layout:
+//!
+//!    (format) (version) (width) (height) (seed) (flags) (move count) (moves) [timings] [metadata] [annotations] (checksum)
+//!
+//! - format is a single byte, [BINARY_FORMAT_VERSION]
+//! - version, width, height, seed, flags and the move count are LEB128 varints
+//! - moves are packed 3 bits each, least significant bits first, using the same codes as the text format
+//! - timings are only present if flag 1 is set: a varint count followed by a varint for each entry
+//! - metadata is only present from version 3 onwards: the [encoded](super::metadata::Metadata::encode) metadata
+//!   prefixed with its length as a varint
+//! - annotations are only present if flag 2 is set: a varint count followed by the [lines](super::annotation::Annotations::lines),
+//!   each prefixed with its length as a varint
+//! - checksum is the CRC-32 of everything before it, little-endian
+
+use thiserror::Error;
+
+use super::{
+    annotation::Annotations,
+    io::{direction_from_code, move_code},
+    metadata::{Metadata, MetadataParseError},
+    recording::SeededRecording,
+    varint::{self, VarintError},
+};
+use crate::{rules::configurable::RulesetError, version};
+
+/// Changes whenever the layout does, older layouts stay readable
+pub const BINARY_FORMAT_VERSION: u8 = 1;
+const TIMINGS_FLAG: u64 = 1;
+const ANNOTATIONS_FLAG: u64 = 2;
+const BITS_PER_MOVE: usize = 3;
+const CHECKSUM_LENGTH: usize = 4;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BinaryRecordingError {
+    #[error("the data is cut short")]
+    Truncated,
+    #[error("the checksum doesn't match")]
+    ChecksumMismatch,
+    #[error("unsupported binary format: {0}")]
+    UnsupportedFormat(u8),
+    #[error("unsupported version: {0}")]
+    UnsupportedVersion(u64),
+    #[error("invalid varint: {0}")]
+    InvalidVarint(#[source] VarintError),
+    #[error("a value is too large")]
+    Overflow,
+    #[error("unknown flags: {0}")]
+    UnknownFlags(u64),
+    #[error("invalid move {0}")]
+    InvalidMove(usize),
+    #[error("invalid UTF-8")]
+    InvalidUtf8,
+    #[error("invalid metadata: {0}")]
+    InvalidMetadata(#[source] MetadataParseError),
+    #[error("invalid ruleset: {0}")]
+    InvalidRuleset(#[source] RulesetError),
+    #[error("unexpected data after the recording")]
+    TrailingData,
+}
+
+impl SeededRecording {
+    /// Encode the recording in the [binary](self) format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![BINARY_FORMAT_VERSION];
+        let mut flags = 0;
+        if self.timings.is_some() {
+            flags |= TIMINGS_FLAG;
+        }
+        if !self.annotations.is_empty() {
+            flags |= ANNOTATIONS_FLAG;
+        }
+        for value in [
+            self.written_version() as u64,
+            self.width as u64,
+            self.height as u64,
+            self.seed as u64,
+            flags,
+            self.moves.len() as u64,
+        ] {
+            varint::write(&mut bytes, value);
+        }
+
+        let mut packed = vec![0u8; (self.moves.len() * BITS_PER_MOVE).div_ceil(8)];
+        for (index, &direction) in self.moves.iter().enumerate() {
+            let bit = index * BITS_PER_MOVE;
+            let code = move_code(direction) as u16;
+            packed[bit / 8] |= (code << (bit % 8)) as u8;
+            if bit % 8 > 8 - BITS_PER_MOVE {
+                packed[bit / 8 + 1] |= (code >> (8 - bit % 8)) as u8;
+            }
+        }
+        bytes.extend(packed);
+
+        if let Some(timings) = &self.timings {
+            varint::write(&mut bytes, timings.len() as u64);
+            for &timing in timings {
+                varint::write(&mut bytes, timing as u64);
+            }
+        }
+        if self.written_version() >= version::SEEDED_METADATA {
+            let metadata = self.written_metadata().encode();
+            varint::write(&mut bytes, metadata.len() as u64);
+            bytes.extend(metadata.as_bytes());
+        }
+        if !self.annotations.is_empty() {
+            varint::write(&mut bytes, self.annotations.0.len() as u64);
+            for line in self.annotations.lines() {
+                varint::write(&mut bytes, line.len() as u64);
+                bytes.extend(line.as_bytes());
+            }
+        }
+
+        let checksum = crc32fast::hash(&bytes);
+        bytes.extend(checksum.to_le_bytes());
+        bytes
+    }
+
+    /// Decode a recording written by [to_bytes](Self::to_bytes)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BinaryRecordingError> {
+        let body_length = bytes
+            .len()
+            .checked_sub(CHECKSUM_LENGTH)
+            .ok_or(BinaryRecordingError::Truncated)?;
+        let (body, checksum) = bytes.split_at(body_length);
+        if crc32fast::hash(body).to_le_bytes() != checksum {
+            return Err(BinaryRecordingError::ChecksumMismatch);
+        }
+
+        let (&format, body) = body.split_first().ok_or(BinaryRecordingError::Truncated)?;
+        if format != BINARY_FORMAT_VERSION {
+            return Err(BinaryRecordingError::UnsupportedFormat(format));
+        }
+        let mut reader = varint::Reader::new(body);
+        let next = |reader: &mut varint::Reader| {
+            reader
+                .next()
+                .ok_or(BinaryRecordingError::Truncated)?
+                .map_err(BinaryRecordingError::InvalidVarint)
+        };
+
+        let number = next(&mut reader)?;
+        let version = match u8::try_from(number) {
+            Ok(version @ (version::SEEDED | version::SEEDED_METADATA)) => version,
+            _ => return Err(BinaryRecordingError::UnsupportedVersion(number)),
+        };
+        let width = to_usize(next(&mut reader)?)?;
+        let height = to_usize(next(&mut reader)?)?;
+        let seed = u32::try_from(next(&mut reader)?).map_err(|_| BinaryRecordingError::Overflow)?;
+        let flags = next(&mut reader)?;
+        if flags & !(TIMINGS_FLAG | ANNOTATIONS_FLAG) != 0 {
+            return Err(BinaryRecordingError::UnknownFlags(flags));
+        }
+
+        let move_count = to_usize(next(&mut reader)?)?;
+        let packed_length = move_count
+            .checked_mul(BITS_PER_MOVE)
+            .ok_or(BinaryRecordingError::Overflow)?
+            .div_ceil(8);
+        let packed = reader
+            .bytes(packed_length)
+            .ok_or(BinaryRecordingError::Truncated)?;
+        let moves = (0..move_count)
+            .map(|index| {
+                let bit = index * BITS_PER_MOVE;
+                let mut code = (packed[bit / 8] as u16) >> (bit % 8);
+                if bit % 8 > 8 - BITS_PER_MOVE {
+                    code |= (packed[bit / 8 + 1] as u16) << (8 - bit % 8);
+                }
+                direction_from_code((code & 0b111) as u8)
+                    .ok_or(BinaryRecordingError::InvalidMove(index))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let timings = match flags & TIMINGS_FLAG {
+            0 => None,
+            _ => {
+                let count = to_usize(next(&mut reader)?)?;
+                Some(
+                    (0..count)
+                        .map(|_| {
+                            u32::try_from(next(&mut reader)?)
+                                .map_err(|_| BinaryRecordingError::Overflow)
+                        })
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+        };
+        let mut metadata = match version {
+            version::SEEDED => Metadata::default(),
+            _ => {
+                let length = to_usize(next(&mut reader)?)?;
+                Metadata::decode(string(&mut reader, length)?)
+                    .map_err(BinaryRecordingError::InvalidMetadata)?
+            }
+        };
+        let annotations = match flags & ANNOTATIONS_FLAG {
+            0 => Annotations::default(),
+            _ => {
+                let count = to_usize(next(&mut reader)?)?;
+                let lines = (0..count)
+                    .map(|_| {
+                        let length = to_usize(next(&mut reader)?)?;
+                        string(&mut reader, length)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Annotations::from_lines(lines)
+            }
+        };
+        if reader.position() != body.len() {
+            return Err(BinaryRecordingError::TrailingData);
+        }
+        let ruleset =
+            Self::read_ruleset(&mut metadata).map_err(BinaryRecordingError::InvalidRuleset)?;
+
+        Ok(Self {
+            version,
+            seed,
+            width,
+            height,
+            moves,
+            ruleset,
+            metadata,
+            timings,
+            annotations,
+        })
+    }
+}
+
+fn string<'a>(
+    reader: &mut varint::Reader<'a>,
+    length: usize,
+) -> Result<&'a str, BinaryRecordingError> {
+    let bytes = reader
+        .bytes(length)
+        .ok_or(BinaryRecordingError::Truncated)?;
+    std::str::from_utf8(bytes).map_err(|_| BinaryRecordingError::InvalidUtf8)
+}
+
+fn to_usize(value: u64) -> Result<usize, BinaryRecordingError> {
+    usize::try_from(value).map_err(|_| BinaryRecordingError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        direction::Direction,
+        unified::hash::Hashable,
+        v2::test_data::{
+            GAME_NI4FIRM, GAME_NI4FIRM_COMMENTED, GAME_NI4FIRM_HASH, GAME_WON_3_BREAKS,
+        },
+    };
+
+    fn assert_round_trip(recording: &SeededRecording) {
+        let bytes = recording.to_bytes();
+        let decoded = SeededRecording::from_bytes(&bytes).unwrap();
+        assert_eq!(&decoded, recording);
+        assert_eq!(String::from(&decoded), String::from(recording));
+        assert_eq!(decoded.game_hash(), recording.game_hash());
+    }
+
+    #[test]
+    fn round_trip() {
+        let recording: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        assert_round_trip(&recording);
+        assert_eq!(
+            SeededRecording::from_bytes(&recording.to_bytes())
+                .unwrap()
+                .game_hash(),
+            GAME_NI4FIRM_HASH
+        );
+        assert!(recording.to_bytes().len() < String::from(&recording).len());
+
+        assert_round_trip(&GAME_WON_3_BREAKS.parse().unwrap());
+        assert_round_trip(&SeededRecording::empty(0, 4, 4));
+    }
+
+    #[test]
+    fn round_trip_timings_and_metadata() {
+        let mut recording: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        recording.timings = Some((0..recording.moves.len() as u32).map(|i| i * 100).collect());
+        assert_round_trip(&recording);
+
+        let mut metadata = Metadata::default();
+        metadata.set(Metadata::PLAYER_ID, "player:1;2");
+        let recording = recording.with_metadata(metadata);
+        assert_round_trip(&recording);
+        assert_round_trip(
+            &SeededRecording::empty(u32::MAX, 5, 3).with_metadata(Metadata::default()),
+        );
+    }
+
+    #[test]
+    fn round_trip_annotations() {
+        let recording: SeededRecording = GAME_NI4FIRM_COMMENTED.parse().unwrap();
+        assert!(!recording.annotations.is_empty());
+        assert_round_trip(&recording);
+    }
+
+    #[test]
+    fn every_move() {
+        // Covers moves packed across byte boundaries at every offset
+        let moves = [
+            Direction::UP,
+            Direction::RIGHT,
+            Direction::DOWN,
+            Direction::LEFT,
+            Direction::BREAK,
+            Direction::END,
+        ];
+        let recording =
+            SeededRecording::new(1, 4, 4, moves.iter().cycle().take(50).copied().collect());
+        assert_round_trip(&recording);
+    }
+
+    #[test]
+    fn invalid() {
+        let recording: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        let bytes = recording.to_bytes();
+        assert_eq!(
+            SeededRecording::from_bytes(&bytes[..6]),
+            Err(BinaryRecordingError::ChecksumMismatch)
+        );
+        assert_eq!(
+            SeededRecording::from_bytes(&[]),
+            Err(BinaryRecordingError::Truncated)
+        );
+
+        let mut corrupted = bytes.clone();
+        corrupted[10] ^= 1;
+        assert_eq!(
+            SeededRecording::from_bytes(&corrupted),
+            Err(BinaryRecordingError::ChecksumMismatch)
+        );
+
+        let with_checksum = |mut body: Vec<u8>| {
+            let checksum = crc32fast::hash(&body);
+            body.extend(checksum.to_le_bytes());
+            body
+        };
+        let mut future = bytes[..bytes.len() - CHECKSUM_LENGTH].to_vec();
+        future[0] = BINARY_FORMAT_VERSION + 1;
+        assert_eq!(
+            SeededRecording::from_bytes(&with_checksum(future)),
+            Err(BinaryRecordingError::UnsupportedFormat(
+                BINARY_FORMAT_VERSION + 1
+            ))
+        );
+
+        let mut trailing = bytes[..bytes.len() - CHECKSUM_LENGTH].to_vec();
+        trailing.push(0);
+        assert_eq!(
+            SeededRecording::from_bytes(&with_checksum(trailing)),
+            Err(BinaryRecordingError::TrailingData)
+        );
+
+        // A single move with the unused code 7
+        assert_eq!(
+            SeededRecording::from_bytes(&with_checksum(vec![
+                BINARY_FORMAT_VERSION,
+                2,
+                4,
+                4,
+                0,
+                0,
+                1,
+                0b111
+            ])),
+            Err(BinaryRecordingError::InvalidMove(0))
+        );
+    }
+}
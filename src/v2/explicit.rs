@@ -0,0 +1,529 @@
+//! Provides [ExplicitRecording], a compact recording that stores the tile spawned after every move instead of a seed.
+//!
+//! v1 [Recording]s store the whole board on every move, while [SeededRecording](super::recording::SeededRecording)s
+//! can only represent games whose tiles were spawned by [lcg_sane](crate::random::lcg_sane).
+//! An [ExplicitRecording] stores the starting board and the spawn of every move, which is enough to convert any
+//! recording the [v1 parser](crate::v1::parser) produces with [From] and back with [ExplicitRecording::to_recording].
+//!
+//! Boards that don't follow from the previous one, like the ones after a break, are stored as is.
+
+use std::{num::ParseIntError, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    io::SEEDED_RECORDING_SEPARATOR,
+    varint::{self, VarintError},
+};
+use crate::{
+    board::{
+        check_move, initialize_tiles,
+        tile::{InitialID, Tile},
+        Board, Tiles,
+    },
+    direction::Direction,
+    rules::{Ruleset, RulesetProvider},
+    unified::{
+        hash::Hashable,
+        reconstruction::{HistoryReconstruction, Reconstructable},
+        validation::{Validatable, ValidationResult},
+    },
+    v1::{recording::Recording, validator::ValidationError},
+    version,
+};
+
+/// The version in the text format
+pub const EXPLICIT_RECORDING_VERSION: u8 = version::EXPLICIT;
+const SPAWN_FLAG: u64 = 1 << 3;
+const BOARD_FLAG: u64 = 1 << 4;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ExplicitRecordingParseError {
+    #[error("unknown format")]
+    UnknownFormat,
+    #[error("missing version information")]
+    MissingVersion,
+    #[error("invalid version information: {0}")]
+    InvalidVersion(#[source] ParseIntError),
+    #[error("unsupported version: {0}")]
+    UnsupportedVersion(u8),
+    #[error("missing width")]
+    MissingWidth,
+    #[error("invalid width: {0}")]
+    InvalidWidth(#[source] ParseIntError),
+    #[error("missing height")]
+    MissingHeight,
+    #[error("invalid height: {0}")]
+    InvalidHeight(#[source] ParseIntError),
+    #[error("missing moves")]
+    MissingMoves,
+    #[error("invalid moves: {0}")]
+    InvalidMoves(#[source] VarintError),
+    #[error("a value is too large")]
+    Overflow,
+    #[error("a {0}x{1} board is too large")]
+    BoardTooLarge(usize, usize),
+    #[error("the starting board is cut short")]
+    TruncatedStart,
+    #[error("move {0} is cut short")]
+    TruncatedMove(usize),
+    #[error("invalid direction or flags on move {0}")]
+    InvalidHeader(usize),
+}
+
+/// A tile added to the board after a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Spawn {
+    pub x: usize,
+    pub y: usize,
+    pub value: usize,
+}
+
+impl From<Tile> for Spawn {
+    fn from(tile: Tile) -> Self {
+        Self {
+            x: tile.x,
+            y: tile.y,
+            value: tile.value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ExplicitStep {
+    pub direction: Direction,
+    /// The tile added after the move
+    pub spawn: Option<Spawn>,
+    /// The values of the board after the move and the spawn, row by row.
+    /// Only stored if they don't follow from the previous board.
+    pub board: Option<Vec<usize>>,
+}
+
+/// A recording with the spawned tiles stored explicitly, see the [module](self) documentation
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ExplicitRecording {
+    pub width: usize,
+    pub height: usize,
+    /// The values of the starting board row by row, 0 for empty cells
+    pub start: Vec<usize>,
+    pub steps: Vec<ExplicitStep>,
+}
+
+impl From<&Recording> for ExplicitRecording {
+    fn from(recording: &Recording) -> Self {
+        let (width, height) = (recording.width, recording.height);
+        let boards: Vec<Vec<usize>> = recording
+            .history
+            .iter()
+            .map(|(tiles, _, _)| values(tiles))
+            .collect();
+        let steps = recording
+            .history
+            .iter()
+            .enumerate()
+            .map(|(index, &(_, direction, addition))| {
+                let spawn = addition.map(Spawn::from);
+                let board = boards
+                    .get(index + 1)
+                    .filter(|&next| {
+                        predict(width, height, &boards[index], direction, spawn) != *next
+                    })
+                    .cloned();
+                ExplicitStep {
+                    direction,
+                    spawn,
+                    board,
+                }
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            start: boards
+                .first()
+                .cloned()
+                .unwrap_or_else(|| vec![0; width * height]),
+            steps,
+        }
+    }
+}
+
+impl ExplicitRecording {
+    /// Replay the moves, giving back the v1 recording this was converted from.
+    ///
+    /// The boards are built the same way the [v1 parser](crate::v1::parser) builds them, so the tiles even get the same ids.
+    pub fn to_recording(&self) -> Recording {
+        let (width, height) = (self.width, self.height);
+        let mut id_counter = 0;
+        let mut values = self.start.clone();
+        let mut history = Vec::with_capacity(self.steps.len());
+        for step in &self.steps {
+            let tiles = tiles(width, height, &values, &mut id_counter);
+            let addition = step.spawn.map(|spawn| {
+                Tile::new(
+                    spawn.x,
+                    spawn.y,
+                    spawn.value,
+                    InitialID::AutoAssign(&mut id_counter),
+                )
+            });
+            history.push((tiles, step.direction, addition));
+            values = match &step.board {
+                Some(board) => board.clone(),
+                None => predict(width, height, &values, step.direction, step.spawn),
+            };
+        }
+        Recording {
+            width,
+            height,
+            history,
+        }
+    }
+}
+
+/// The values of the board row by row, 0 for empty cells
+fn values(tiles: &Tiles) -> Vec<usize> {
+    tiles
+        .iter()
+        .flatten()
+        .map(|tile| tile.map(|t| t.value).unwrap_or(0))
+        .collect()
+}
+
+fn tiles(width: usize, height: usize, values: &[usize], id_counter: &mut usize) -> Tiles {
+    let mut tiles = initialize_tiles(width, height, id_counter);
+    for (index, &value) in values.iter().enumerate() {
+        let (x, y) = (index % width, index / width);
+        if y < height {
+            tiles[y][x] = Some(Tile::new(x, y, value, InitialID::AutoAssign(id_counter)));
+        }
+    }
+    tiles
+}
+
+/// The values of the board after the move and the spawn
+fn predict(
+    width: usize,
+    height: usize,
+    values: &[usize],
+    direction: Direction,
+    spawn: Option<Spawn>,
+) -> Vec<usize> {
+    let tiles = tiles(width, height, values, &mut 0);
+    let mut predicted = match check_move(Board::from((tiles.clone(), 0)), direction) {
+        Ok(result) => self::values(&result.board.tiles),
+        Err(_) => self::values(&tiles),
+    };
+    if let Some(spawn) = spawn {
+        if spawn.x < width && spawn.y < height {
+            predicted[spawn.y * width + spawn.x] = spawn.value;
+        }
+    }
+    predicted
+}
+
+fn direction_code(direction: Direction) -> u64 {
+    match direction {
+        Direction::UP => 0,
+        Direction::RIGHT => 1,
+        Direction::DOWN => 2,
+        Direction::LEFT => 3,
+        Direction::END => 4,
+        Direction::START => 5,
+        Direction::BREAK => 6,
+    }
+}
+
+fn direction_from_code(code: u64) -> Option<Direction> {
+    match code {
+        0 => Some(Direction::UP),
+        1 => Some(Direction::RIGHT),
+        2 => Some(Direction::DOWN),
+        3 => Some(Direction::LEFT),
+        4 => Some(Direction::END),
+        5 => Some(Direction::START),
+        6 => Some(Direction::BREAK),
+        _ => None,
+    }
+}
+
+impl Hashable for ExplicitRecording {
+    /// The same hash as the v1 [Recording], so converting a recording doesn't change its hash
+    fn game_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.width.to_string().as_bytes());
+        hasher.update(self.height.to_string().as_bytes());
+        for step in &self.steps {
+            hasher.update(step.direction.get_shorthand().as_bytes());
+        }
+        format!("{:X}", hasher.finalize())
+    }
+}
+
+impl Validatable for ExplicitRecording {
+    type Error = ValidationError;
+    fn validate(&self) -> Result<ValidationResult, Self::Error> {
+        self.to_recording().validate()
+    }
+}
+
+impl Reconstructable for ExplicitRecording {
+    type ReconstructionError = ValidationError;
+    fn reconstruct(&self) -> Result<HistoryReconstruction, Self::ReconstructionError> {
+        self.to_recording().reconstruct()
+    }
+}
+
+impl RulesetProvider for ExplicitRecording {
+    fn rules(&self) -> &dyn Ruleset {
+        crate::rules::for_version(version::EXPLICIT)
+    }
+}
+
+/// Converts a string to an [ExplicitRecording].
+///
+/// Schema:
+///
+///    ::4:(width):(height):(moves)\n(arbitrary data)
+///
+/// where moves is a base64 encoded string of LEB128 varints: the values of the starting board row by row, followed by
+/// each move as its direction (0: Up, 1: Right, 2: Down, 3: Left, 4: End, 5: Start, 6: Break) plus 8 if a tile was spawned
+/// and 16 if the board is stored. The x, y and value of the spawned tile and the values of the stored board follow.
+impl FromStr for ExplicitRecording {
+    type Err = ExplicitRecordingParseError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        // We only care about the first line to allow for comments
+        let first_line = data.lines().next().unwrap_or(data);
+        let mut split = first_line.split(SEEDED_RECORDING_SEPARATOR);
+        if split.next() != Some("") || split.next() != Some("") {
+            return Err(ExplicitRecordingParseError::UnknownFormat);
+        }
+        let version = split
+            .next()
+            .ok_or(ExplicitRecordingParseError::MissingVersion)?
+            .parse::<u8>()
+            .map_err(ExplicitRecordingParseError::InvalidVersion)?;
+        if version != EXPLICIT_RECORDING_VERSION {
+            return Err(ExplicitRecordingParseError::UnsupportedVersion(version));
+        }
+        let width = split
+            .next()
+            .ok_or(ExplicitRecordingParseError::MissingWidth)?
+            .parse::<usize>()
+            .map_err(ExplicitRecordingParseError::InvalidWidth)?;
+        let height = split
+            .next()
+            .ok_or(ExplicitRecordingParseError::MissingHeight)?
+            .parse::<usize>()
+            .map_err(ExplicitRecordingParseError::InvalidHeight)?;
+        let values = varint::decode(
+            split
+                .next()
+                .ok_or(ExplicitRecordingParseError::MissingMoves)?,
+        )
+        .map_err(ExplicitRecordingParseError::InvalidMoves)?
+        .into_iter()
+        .map(usize::try_from)
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| ExplicitRecordingParseError::Overflow)?;
+
+        let size = width
+            .checked_mul(height)
+            .ok_or(ExplicitRecordingParseError::BoardTooLarge(width, height))?;
+        let mut values = values.into_iter();
+        let start: Vec<usize> = values.by_ref().take(size).collect();
+        if start.len() < size {
+            return Err(ExplicitRecordingParseError::TruncatedStart);
+        }
+        let mut steps = vec![];
+        while let Some(header) = values.next() {
+            let index = steps.len();
+            let header = header as u64;
+            let direction = direction_from_code(header & 0b111)
+                .filter(|_| header & !(0b111 | SPAWN_FLAG | BOARD_FLAG) == 0)
+                .ok_or(ExplicitRecordingParseError::InvalidHeader(index))?;
+            let mut next = || {
+                values
+                    .next()
+                    .ok_or(ExplicitRecordingParseError::TruncatedMove(index))
+            };
+            let spawn = match header & SPAWN_FLAG {
+                0 => None,
+                _ => Some(Spawn {
+                    x: next()?,
+                    y: next()?,
+                    value: next()?,
+                }),
+            };
+            let board = match header & BOARD_FLAG {
+                0 => None,
+                _ => Some((0..size).map(|_| next()).collect::<Result<_, _>>()?),
+            };
+            steps.push(ExplicitStep {
+                direction,
+                spawn,
+                board,
+            });
+        }
+        Ok(Self {
+            width,
+            height,
+            start,
+            steps,
+        })
+    }
+}
+
+impl From<&ExplicitRecording> for String {
+    fn from(recording: &ExplicitRecording) -> Self {
+        let mut values: Vec<u64> = recording.start.iter().map(|&v| v as u64).collect();
+        for step in &recording.steps {
+            let mut header = direction_code(step.direction);
+            if step.spawn.is_some() {
+                header |= SPAWN_FLAG;
+            }
+            if step.board.is_some() {
+                header |= BOARD_FLAG;
+            }
+            values.push(header);
+            if let Some(spawn) = step.spawn {
+                values.extend([spawn.x as u64, spawn.y as u64, spawn.value as u64]);
+            }
+            if let Some(board) = &step.board {
+                values.extend(board.iter().map(|&v| v as u64));
+            }
+        }
+        format!(
+            "{sep}{sep}{}{sep}{}{sep}{}{sep}{}",
+            EXPLICIT_RECORDING_VERSION,
+            recording.width,
+            recording.height,
+            varint::encode(values),
+            sep = SEEDED_RECORDING_SEPARATOR
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::{parser::parse_data, tests::lib_testgames};
+
+    fn assert_lossless(data: &str) {
+        let recording = parse_data(data).unwrap();
+        let explicit = ExplicitRecording::from(&recording);
+        let text = String::from(&explicit);
+        assert!(text.len() < recording.to_string().len());
+
+        let parsed: ExplicitRecording = text.parse().unwrap();
+        assert_eq!(parsed, explicit);
+        let converted = parsed.to_recording();
+        assert_eq!(converted, recording);
+        assert_eq!(
+            serde_json::to_string(&converted).unwrap(),
+            serde_json::to_string(&recording).unwrap()
+        );
+        assert_eq!(parsed.game_hash(), recording.game_hash());
+        assert_eq!(
+            parsed.validate().map_err(|e| e.to_string()),
+            recording.validate().map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn lossless() {
+        for data in [
+            lib_testgames::GAME3X3,
+            lib_testgames::GAME3X3B,
+            lib_testgames::GAME4X4,
+            lib_testgames::GAME4X4B,
+            lib_testgames::GAME4X4C,
+            lib_testgames::GAMEOBSCENE,
+        ] {
+            assert_lossless(data);
+        }
+    }
+
+    #[test]
+    fn stores_unpredictable_boards() {
+        let mut recording = parse_data(lib_testgames::GAME4X4).unwrap();
+        recording.history[5].0[0][0].as_mut().unwrap().value = 2048;
+        let explicit = ExplicitRecording::from(&recording);
+        let stored: Vec<usize> = explicit
+            .steps
+            .iter()
+            .enumerate()
+            .filter(|(_, step)| step.board.is_some())
+            .map(|(index, _)| index)
+            .collect();
+        assert!(stored.contains(&4));
+        assert_eq!(explicit.to_recording(), recording);
+        assert!(explicit.validate().is_err());
+    }
+
+    #[test]
+    fn empty() {
+        let recording = Recording {
+            width: 4,
+            height: 4,
+            history: vec![],
+        };
+        let explicit = ExplicitRecording::from(&recording);
+        let parsed: ExplicitRecording = String::from(&explicit).parse().unwrap();
+        assert_eq!(parsed.to_recording(), recording);
+    }
+
+    #[test]
+    fn invalid() {
+        let explicit = ExplicitRecording::from(&parse_data(lib_testgames::GAME3X3).unwrap());
+        let text = String::from(&explicit);
+        assert_eq!(
+            text.replacen("::4:", "::2:", 1)
+                .parse::<ExplicitRecording>(),
+            Err(ExplicitRecordingParseError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            "::4:3:3:AAAA".parse::<ExplicitRecording>(),
+            Err(ExplicitRecordingParseError::TruncatedStart)
+        );
+        let start = varint::encode([0; 9]);
+        assert_eq!(
+            format!("::4:3:3:{}", varint::encode([0; 9].into_iter().chain([7])))
+                .parse::<ExplicitRecording>(),
+            Err(ExplicitRecordingParseError::InvalidHeader(0))
+        );
+        assert_eq!(
+            format!(
+                "::4:3:3:{}",
+                varint::encode([0; 9].into_iter().chain([SPAWN_FLAG, 1]))
+            )
+            .parse::<ExplicitRecording>(),
+            Err(ExplicitRecordingParseError::TruncatedMove(0))
+        );
+        assert_eq!(
+            format!("::4:{}:3:{}", usize::MAX, start).parse::<ExplicitRecording>(),
+            Err(ExplicitRecordingParseError::BoardTooLarge(usize::MAX, 3))
+        );
+        assert!(format!("::4:3:3:{}", start)
+            .parse::<ExplicitRecording>()
+            .is_ok());
+    }
+
+    #[test]
+    #[ignore = "slow"]
+    fn lossless_all_real() {
+        use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+        let games: Vec<&str> = lib_testgames::GAMELIST.lines().collect();
+        games.par_iter().for_each(|game| assert_lossless(game));
+    }
+}
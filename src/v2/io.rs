@@ -3,12 +3,21 @@ use std::{num::ParseIntError, str::FromStr};
 use base64::Engine;
 use thiserror::Error;
 
-use crate::direction::Direction;
+use crate::{
+    direction::Direction, encoding::get_b64_engine, rules::configurable::RulesetError, version,
+};
 
-use super::recording::SeededRecording;
+use super::{
+    annotation::Annotations,
+    metadata::{Metadata, MetadataParseError},
+    recording::SeededRecording,
+    timing::{self, TimingParseError},
+};
 
 // do NOT change this, it will break all existing seeded recordings
 pub(crate) const SEEDED_RECORDING_SEPARATOR: &str = ":";
+/// Separates the optional timings from the moves, not part of the base64 alphabet
+const TIMINGS_SEPARATOR: char = '.';
 
 #[derive(Error, Debug, Clone)]
 pub enum SeededRecordingParseError {
@@ -38,13 +47,28 @@ pub enum SeededRecordingParseError {
     MissingMoves,
     #[error("invalid move")]
     InvalidMove,
+    #[error("missing metadata")]
+    MissingMetadata,
+    #[error("invalid metadata: {0}")]
+    InvalidMetadata(#[source] MetadataParseError),
+    #[error("invalid timings: {0}")]
+    InvalidTimings(#[source] TimingParseError),
+    #[error("invalid ruleset: {0}")]
+    InvalidRuleset(#[source] RulesetError),
 }
 
 /// Converts a string to a [SeededRecording].
 ///
 /// Schema:
 ///
-///    ::(version):(width):(height):(seed):(moves)\n(arbitrary data)
+///    ::(version):(width):(height):(seed):(moves)\n(annotations)
+///
+/// Version 3 adds the [Metadata] of the recording after the moves:
+///
+///    ::3:(width):(height):(seed):(moves):(metadata)\n(annotations)
+///
+/// In either version the moves may be followed by `.(timings)`, see [timing](super::timing).
+/// The lines after the first one are [Annotations], one per line.
 ///
 /// where moves is a base64 encoded string of the moves, each move is represented by a single byte with 6 possible states:
 ///
@@ -63,8 +87,9 @@ impl FromStr for SeededRecording {
     type Err = SeededRecordingParseError;
 
     fn from_str(data: &str) -> Result<Self, Self::Err> {
-        // We only care about the first line to allow for comments
-        let first_line = data.lines().next().unwrap_or(data);
+        // The recording is on the first line, the rest are annotations
+        let mut lines = data.lines();
+        let first_line = lines.next().unwrap_or(data);
         let mut split = first_line.split(SEEDED_RECORDING_SEPARATOR);
         let _reserved_space_start = split
             .next()
@@ -77,19 +102,40 @@ impl FromStr for SeededRecording {
             .ok_or(SeededRecordingParseError::MissingVersion)?
             .parse::<u8>()
             .map_err(SeededRecordingParseError::InvalidVersion)?;
-        match version {
-            2 => parse_v2(
-                split
-                    .collect::<Vec<_>>()
-                    .join(SEEDED_RECORDING_SEPARATOR)
-                    .as_str(),
-            ),
+        let data = split.collect::<Vec<_>>().join(SEEDED_RECORDING_SEPARATOR);
+        let mut recording = match version {
+            version::SEEDED => parse_v2(data.as_str()),
+            version::SEEDED_METADATA => parse_v3(data.as_str()),
             _ => Err(SeededRecordingParseError::UnsupportedVersion(version)),
-        }
+        }?;
+        recording.annotations = Annotations::from_lines(lines);
+        Ok(recording)
     }
 }
 fn parse_v2(data: &str) -> Result<SeededRecording, SeededRecordingParseError> {
+    parse_body(&mut data.split(SEEDED_RECORDING_SEPARATOR))
+}
+
+/// Same as v2 with the metadata after the moves. Any fields after the metadata are ignored.
+fn parse_v3(data: &str) -> Result<SeededRecording, SeededRecordingParseError> {
     let mut split = data.split(SEEDED_RECORDING_SEPARATOR);
+    let mut recording = parse_body(&mut split)?;
+    let metadata = split
+        .next()
+        .ok_or(SeededRecordingParseError::MissingMetadata)?;
+    recording.version = version::SEEDED_METADATA;
+    let mut metadata =
+        Metadata::decode(metadata).map_err(SeededRecordingParseError::InvalidMetadata)?;
+    recording.ruleset = SeededRecording::read_ruleset(&mut metadata)
+        .map_err(SeededRecordingParseError::InvalidRuleset)?;
+    recording.metadata = metadata;
+    Ok(recording)
+}
+
+/// Parse the fields shared by v2 and v3, returning a v2 recording
+fn parse_body<'a>(
+    split: &mut impl Iterator<Item = &'a str>,
+) -> Result<SeededRecording, SeededRecordingParseError> {
     let width = split
         .next()
         .ok_or(SeededRecordingParseError::MissingWidth)?
@@ -108,6 +154,13 @@ fn parse_v2(data: &str) -> Result<SeededRecording, SeededRecordingParseError> {
     let moves = split
         .next()
         .ok_or(SeededRecordingParseError::MissingMoves)?;
+    let (moves, timings) = match moves.split_once(TIMINGS_SEPARATOR) {
+        Some((moves, timings)) => (
+            moves,
+            Some(timing::decode(timings).map_err(SeededRecordingParseError::InvalidTimings)?),
+        ),
+        None => (moves, None),
+    };
     let mut base = convert_base::Convert::new(64, 6);
     let b64 = get_b64_engine();
     let coded = b64
@@ -116,22 +169,19 @@ fn parse_v2(data: &str) -> Result<SeededRecording, SeededRecordingParseError> {
     let moves = base.convert::<u8, u8>(&coded);
     let moves = moves
         .iter()
-        .map(|dir| match dir {
-            1 => Direction::UP,
-            2 => Direction::RIGHT,
-            3 => Direction::DOWN,
-            4 => Direction::LEFT,
-            5 => Direction::BREAK,
-            _ => Direction::END,
-        })
+        .map(|&code| direction_from_code(code).unwrap_or(Direction::END))
         .collect::<Vec<Direction>>();
 
     Ok(SeededRecording {
-        version: 2,
+        version: version::SEEDED,
         seed,
         width,
         height,
         moves,
+        ruleset: None,
+        metadata: Metadata::default(),
+        timings,
+        annotations: Annotations::default(),
     })
 }
 
@@ -140,7 +190,7 @@ impl From<&SeededRecording> for String {
         let mut out = String::new();
         out += SEEDED_RECORDING_SEPARATOR; // reserved space start
         out += SEEDED_RECORDING_SEPARATOR; // reserved space end
-        out += recording.version.to_string().as_str();
+        out += recording.written_version().to_string().as_str();
         out += SEEDED_RECORDING_SEPARATOR;
         out += recording.width.to_string().as_str();
         out += SEEDED_RECORDING_SEPARATOR;
@@ -149,38 +199,62 @@ impl From<&SeededRecording> for String {
         out += recording.seed.to_string().as_str();
         out += SEEDED_RECORDING_SEPARATOR;
         let mut base = convert_base::Convert::new(6, 64);
-        let input: Vec<u8> = recording
-            .moves
-            .iter()
-            .map(|dir| match dir {
-                Direction::UP => 1,
-                Direction::RIGHT => 2,
-                Direction::DOWN => 3,
-                Direction::LEFT => 4,
-                Direction::BREAK => 5,
-                Direction::END => 0,
-                Direction::START => 0,
-            })
-            .collect();
+        let input: Vec<u8> = recording.moves.iter().map(|&dir| move_code(dir)).collect();
         let z = base.convert::<u8, u8>(&input);
         let b64 = get_b64_engine();
         let moves = b64.encode(z);
         out += moves.as_str();
+        if let Some(timings) = &recording.timings {
+            out.push(TIMINGS_SEPARATOR);
+            out += timing::encode(timings).as_str();
+        }
+        if recording.written_version() >= version::SEEDED_METADATA {
+            out += SEEDED_RECORDING_SEPARATOR;
+            out += recording.written_metadata().encode().as_str();
+        }
+        for line in recording.annotations.lines() {
+            out.push('\n');
+            out += line.as_str();
+        }
         out
     }
 }
 
-fn get_b64_engine() -> base64::engine::general_purpose::GeneralPurpose {
-    base64::engine::general_purpose::STANDARD_NO_PAD
+/// The code of a move in the text and [binary](super::binary) formats
+pub(crate) fn move_code(direction: Direction) -> u8 {
+    match direction {
+        Direction::UP => 1,
+        Direction::RIGHT => 2,
+        Direction::DOWN => 3,
+        Direction::LEFT => 4,
+        Direction::BREAK => 5,
+        Direction::END => 0,
+        Direction::START => 0,
+    }
+}
+
+/// The move of a code written by [move_code], None for codes that aren't used
+pub(crate) fn direction_from_code(code: u8) -> Option<Direction> {
+    match code {
+        0 => Some(Direction::END),
+        1 => Some(Direction::UP),
+        2 => Some(Direction::RIGHT),
+        3 => Some(Direction::DOWN),
+        4 => Some(Direction::LEFT),
+        5 => Some(Direction::BREAK),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        unified::hash::Hashable,
-        v2::{recording::SeededRecording, test_data},
+        unified::{hash::Hashable, validation::Validatable},
+        v2::{annotation::Annotation, metadata::Metadata, recording::SeededRecording, test_data},
     };
 
+    use super::SeededRecordingParseError;
+
     #[test]
     fn parse() {
         let data = test_data::GAME_NI4FIRM;
@@ -201,4 +275,116 @@ mod tests {
             test_data::GAME_NI4FIRM_HASH
         );
     }
+
+    #[test]
+    fn annotations_round_trip() {
+        let mut recording = test_data::GAME_NI4FIRM.parse::<SeededRecording>().unwrap();
+        recording.annotations.push(Annotation::Note {
+            index: 4,
+            text: "merging\ntwice".to_string(),
+        });
+        recording.annotations.push(Annotation::Bookmark {
+            index: 70,
+            label: Some("the end".to_string()),
+        });
+        let data = String::from(&recording);
+        assert_eq!(data.lines().count(), 3);
+        let parsed = data.parse::<SeededRecording>().unwrap();
+        assert_eq!(parsed, recording);
+        assert_eq!(parsed.game_hash(), test_data::GAME_NI4FIRM_HASH);
+
+        let commented = test_data::GAME_NI4FIRM_COMMENTED
+            .parse::<SeededRecording>()
+            .unwrap();
+        assert!(matches!(commented.annotations.0[0], Annotation::Text(_)));
+        assert_eq!(
+            String::from(&commented).parse::<SeededRecording>().unwrap(),
+            commented
+        );
+
+        let malformed = format!("{}\n@note x", test_data::GAME_NI4FIRM.trim())
+            .parse::<SeededRecording>()
+            .unwrap();
+        assert_eq!(
+            malformed.annotations.0,
+            vec![Annotation::Text("@note x".to_string())]
+        );
+    }
+
+    #[test]
+    fn v2_unchanged() {
+        let data = test_data::GAME_NI4FIRM;
+        let parsed = data.parse::<SeededRecording>().unwrap();
+        assert_eq!(parsed.version, 2);
+        assert_eq!(String::from(&parsed), data.lines().next().unwrap());
+    }
+
+    #[test]
+    fn v3_round_trip() {
+        let v2 = test_data::GAME_NI4FIRM.parse::<SeededRecording>().unwrap();
+        let mut metadata = Metadata::default();
+        metadata.set(Metadata::PLAYER_ID, "player:1");
+        metadata.set(Metadata::GAME_MODE, "classic");
+        let v3 = v2.clone().with_metadata(metadata);
+        let data = String::from(&v3);
+        assert!(data.starts_with("::3:"));
+
+        let parsed = format!("{}\ncomment", data)
+            .parse::<SeededRecording>()
+            .unwrap();
+        assert_eq!(
+            parsed.annotations.0,
+            vec![Annotation::Text("comment".to_string())]
+        );
+        assert_eq!(parsed.game_hash(), v3.game_hash());
+        let parsed = data.parse::<SeededRecording>().unwrap();
+        assert_eq!(parsed, v3);
+        assert_eq!(parsed.metadata.player_id(), Some("player:1"));
+        assert_eq!(parsed.validate().unwrap(), v2.validate().unwrap());
+        assert!(parsed.game_hash().starts_with("V3"));
+        assert_ne!(parsed.game_hash(), v2.game_hash());
+    }
+
+    #[test]
+    fn v3_invalid_ruleset() {
+        let v2 = test_data::GAME_NI4FIRM.parse::<SeededRecording>().unwrap();
+        let data = String::from(&v2).replacen("::2:", "::3:", 1);
+        assert!(matches!(
+            format!("{}:rules=%7B", data).parse::<SeededRecording>(),
+            Err(SeededRecordingParseError::InvalidRuleset(_))
+        ));
+    }
+
+    #[test]
+    fn v3_invalid_metadata() {
+        let v2 = test_data::GAME_NI4FIRM.parse::<SeededRecording>().unwrap();
+        let data = String::from(&v2).replacen("::2:", "::3:", 1);
+        assert!(matches!(
+            data.parse::<SeededRecording>(),
+            Err(SeededRecordingParseError::MissingMetadata)
+        ));
+        assert!(matches!(
+            format!("{}:player", data).parse::<SeededRecording>(),
+            Err(SeededRecordingParseError::InvalidMetadata(_))
+        ));
+        assert!(format!("{}:", data).parse::<SeededRecording>().is_ok());
+    }
+
+    #[test]
+    fn timings_round_trip() {
+        let mut recording = test_data::GAME_NI4FIRM.parse::<SeededRecording>().unwrap();
+        recording.timings = Some((0..recording.moves.len() as u32).map(|i| i * 37).collect());
+        let data = String::from(&recording);
+        let parsed = data.parse::<SeededRecording>().unwrap();
+        assert_eq!(parsed, recording);
+        assert_eq!(parsed.game_hash(), test_data::GAME_NI4FIRM_HASH);
+
+        let v3 = recording.with_metadata(Metadata::default());
+        assert_eq!(String::from(&v3).parse::<SeededRecording>().unwrap(), v3);
+
+        assert!(matches!(
+            format!("{}!", data).parse::<SeededRecording>(),
+            Err(SeededRecordingParseError::InvalidTimings(_))
+        ));
+    }
 }
@@ -0,0 +1,194 @@
+//! Provides [Metadata], the key/value pairs carried by version 3 of the [SeededRecording](super::recording::SeededRecording) format.
+//!
+//! In the text format the pairs are written as `key=value` separated by `;`, sorted by key.
+//! Keys and values are percent-encoded, so they may contain any characters.
+//! Unknown keys are kept as they are, which allows new keys to be added without a new version of the format.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const PAIR_SEPARATOR: char = ';';
+const KEY_VALUE_SEPARATOR: char = '=';
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MetadataParseError {
+    #[error("metadata entry `{0}` is missing a value")]
+    MissingValue(String),
+    #[error("invalid percent-encoding in `{0}`")]
+    InvalidEncoding(String),
+    #[error("metadata key `{0}` appears more than once")]
+    DuplicateKey(String),
+}
+
+/// Key/value metadata of a recording
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(transparent)]
+pub struct Metadata(pub BTreeMap<String, String>);
+
+impl Metadata {
+    /// Identifies the player
+    pub const PLAYER_ID: &'static str = "player";
+    /// The build of the client the game was played on
+    pub const CLIENT_BUILD: &'static str = "client";
+    /// The id of the [ConfigurableRuleset](crate::rules::configurable::ConfigurableRuleset) the game was played with
+    pub const RULESET_ID: &'static str = "ruleset";
+    /// When the game was started, as seconds since the unix epoch
+    pub const STARTED_AT: &'static str = "started";
+    /// The game mode, as defined by the client
+    pub const GAME_MODE: &'static str = "mode";
+    /// The [name](crate::random::RandAlgo::name) of the random number generator the tiles are spawned with
+    pub const RNG: &'static str = "rng";
+    /// The [canonical](crate::rules::configurable::ConfigurableRuleset::canonical) spec of the custom ruleset.
+    ///
+    /// Written and read by the recording formats, the parsed ruleset is found in
+    /// [SeededRecording::ruleset](super::recording::SeededRecording::ruleset) instead.
+    pub const RULES: &'static str = "rules";
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Set a value, returning the previous one if there was one
+    pub fn set(&mut self, key: &str, value: &str) -> Option<String> {
+        self.0.insert(key.to_string(), value.to_string())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn player_id(&self) -> Option<&str> {
+        self.get(Self::PLAYER_ID)
+    }
+
+    pub fn client_build(&self) -> Option<&str> {
+        self.get(Self::CLIENT_BUILD)
+    }
+
+    pub fn ruleset_id(&self) -> Option<&str> {
+        self.get(Self::RULESET_ID)
+    }
+
+    /// The start timestamp, None if it's missing or not a number
+    pub fn started_at(&self) -> Option<u64> {
+        self.get(Self::STARTED_AT)?.parse().ok()
+    }
+
+    pub fn game_mode(&self) -> Option<&str> {
+        self.get(Self::GAME_MODE)
+    }
+
+    pub fn rng(&self) -> Option<&str> {
+        self.get(Self::RNG)
+    }
+
+    /// Encode the metadata for the text format
+    pub fn encode(&self) -> String {
+        self.0
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{}{}{}",
+                    percent_encode(key),
+                    KEY_VALUE_SEPARATOR,
+                    percent_encode(value)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(&PAIR_SEPARATOR.to_string())
+    }
+
+    /// Decode metadata written by [Metadata::encode]
+    pub fn decode(data: &str) -> Result<Self, MetadataParseError> {
+        let mut metadata = Metadata::default();
+        for pair in data.split(PAIR_SEPARATOR).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once(KEY_VALUE_SEPARATOR)
+                .ok_or_else(|| MetadataParseError::MissingValue(pair.to_string()))?;
+            let key = percent_decode(key)?;
+            if metadata.0.contains_key(&key) {
+                return Err(MetadataParseError::DuplicateKey(key));
+            }
+            metadata.0.insert(key, percent_decode(value)?);
+        }
+        Ok(metadata)
+    }
+}
+
+/// Escape everything but unreserved URL characters, so the result never contains a separator of the text format
+fn percent_encode(data: &str) -> String {
+    let mut out = String::with_capacity(data.len());
+    for byte in data.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out += &format!("%{:02X}", byte),
+        }
+    }
+    out
+}
+
+fn percent_decode(data: &str) -> Result<String, MetadataParseError> {
+    let invalid = || MetadataParseError::InvalidEncoding(data.to_string());
+    let mut bytes = Vec::with_capacity(data.len());
+    let mut iter = data.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [
+                iter.next().ok_or_else(invalid)?,
+                iter.next().ok_or_else(invalid)?,
+            ];
+            let hex = std::str::from_utf8(&hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut metadata = Metadata::default();
+        metadata.set(Metadata::PLAYER_ID, "Elias Eskelinen");
+        metadata.set(Metadata::STARTED_AT, "1700000000");
+        metadata.set(Metadata::GAME_MODE, "daily:2024;=%ä");
+        metadata.set("custom", "");
+        let encoded = metadata.encode();
+        assert!(!encoded.contains(':') && !encoded.contains('\n'));
+        assert_eq!(Metadata::decode(&encoded).unwrap(), metadata);
+        assert_eq!(metadata.started_at(), Some(1700000000));
+        assert_eq!(metadata.player_id(), Some("Elias Eskelinen"));
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(Metadata::default().encode(), "");
+        assert!(Metadata::decode("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            Metadata::decode("player"),
+            Err(MetadataParseError::MissingValue("player".to_string()))
+        );
+        assert_eq!(
+            Metadata::decode("player=%4"),
+            Err(MetadataParseError::InvalidEncoding("%4".to_string()))
+        );
+        assert_eq!(
+            Metadata::decode("a=1;a=2"),
+            Err(MetadataParseError::DuplicateKey("a".to_string()))
+        );
+    }
+}
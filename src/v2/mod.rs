@@ -3,9 +3,17 @@
 //! Designed to be simpler and more flexible than [v1](crate::v1).
 //!
 //! See [SeededRecording](crate::v2::recording::SeededRecording) for more details.
+pub mod annotation;
+pub mod binary;
+pub mod explicit;
 pub mod io;
+pub mod metadata;
 pub mod recording;
 pub mod replay;
+pub mod stream;
+pub mod string_form;
+pub mod timing;
+pub mod varint;
 
 #[cfg(test)]
 pub mod test_data;
@@ -1,15 +1,24 @@
 use serde::{Deserialize, Serialize};
 
-use super::replay::MoveReplayError;
+use super::{
+    annotation::Annotations,
+    metadata::Metadata,
+    replay::{replay_moves_packed, MoveReplayError},
+};
 use crate::{
     board::MoveError,
     direction::Direction,
-    rules::{ClassicV1, ClassicV2, Ruleset, RulesetProvider},
+    random::RandAlgo,
+    rules::{
+        configurable::{ConfigurableRuleset, RulesetError},
+        Ruleset, RulesetProvider,
+    },
     unified::{
         hash::Hashable,
         reconstruction::Reconstructable,
         validation::{Validatable, ValidationResult},
     },
+    version,
 };
 
 /// Represents a seeded recording of a played game of 2048.
@@ -29,6 +38,25 @@ pub struct SeededRecording {
     pub height: usize,
     #[serde(alias = "m")]
     pub moves: Vec<Direction>,
+    /// Custom rules to use instead of the ones of the version.
+    ///
+    /// The recording formats carry the [canonical](ConfigurableRuleset::canonical) spec in the [metadata](Metadata::RULES),
+    /// so a recording with a custom ruleset is written as version 3.
+    #[serde(alias = "r", default, skip_serializing_if = "Option::is_none")]
+    pub ruleset: Option<ConfigurableRuleset>,
+    /// Key/value data about the game, only carried by the text format from version 3 onwards
+    #[serde(alias = "d", default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    /// Milliseconds between each move and the one before it, see [timing](super::timing)
+    ///
+    /// Not part of the [game hash](Hashable::game_hash), the same game played at a different pace is still the same game.
+    #[serde(alias = "t", default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Vec<u32>>,
+    /// Commentary on the game, carried on the lines after the recording in the text format, see [annotation](super::annotation)
+    ///
+    /// Not part of the [game hash](Hashable::game_hash) either, commenting on a game doesn't change it.
+    #[serde(alias = "a", default, skip_serializing_if = "Annotations::is_empty")]
+    pub annotations: Annotations,
 }
 
 #[derive(thiserror::Error, Debug, Clone, PartialEq)]
@@ -39,31 +67,116 @@ pub enum BoardFetchError {
     MoveError(MoveError, usize),
 }
 
-const SEEDED_RECORDING_CURRENT_VERSION: u8 = 2;
 impl SeededRecording {
     pub fn new(seed: u32, width: usize, height: usize, moves: Vec<Direction>) -> Self {
         Self {
-            version: SEEDED_RECORDING_CURRENT_VERSION,
+            version: version::SEEDED,
             seed,
             width,
             height,
             moves,
+            ruleset: None,
+            metadata: Metadata::default(),
+            timings: None,
+            annotations: Annotations::default(),
         }
     }
     pub fn empty(seed: u32, width: usize, height: usize) -> Self {
         Self::new(seed, width, height, vec![])
     }
 
+    /// Attach metadata to the recording, upgrading it to version 3 so that the text format carries it
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.version = self.version.max(version::SEEDED_METADATA);
+        self.metadata = metadata;
+        self
+    }
+
+    /// Pick the random number generator of the recording, upgrading it to version 3 so that the text format carries it
+    pub fn with_rng(mut self, rng: RandAlgo) -> Self {
+        self.version = self.version.max(version::SEEDED_METADATA);
+        self.metadata.set(Metadata::RNG, rng.name());
+        self
+    }
+
+    /// The random number generator named by the [metadata](Metadata::RNG) from version 3 onwards, the one of the
+    /// version otherwise. Check that there is one with [check_rng](Self::check_rng).
+    pub fn rng(&self) -> RandAlgo {
+        self.rng_checked().unwrap_or_default()
+    }
+
+    /// Check that the recording names a known random number generator, or that its version has one
+    pub fn check_rng(&self) -> Result<(), MoveReplayError> {
+        self.rng_checked().map(|_| ())
+    }
+
+    fn rng_checked(&self) -> Result<RandAlgo, MoveReplayError> {
+        match self.metadata.rng() {
+            Some(name) if self.version >= version::SEEDED_METADATA => RandAlgo::from_name(name)
+                .ok_or_else(|| MoveReplayError::UnknownRng(name.to_string())),
+            _ => crate::random::for_version(self.version)
+                .ok_or(MoveReplayError::UnsupportedVersion(self.version)),
+        }
+    }
+
+    /// Use a custom ruleset, upgrading the recording to version 3 so that the text format carries it
+    pub fn with_ruleset(mut self, ruleset: ConfigurableRuleset) -> Self {
+        self.version = self.version.max(version::SEEDED_METADATA);
+        self.ruleset = Some(ruleset);
+        self
+    }
+
+    /// The version the recording is written with, custom rulesets need the metadata of version 3
+    pub(crate) fn written_version(&self) -> u8 {
+        match self.ruleset {
+            Some(_) => self.version.max(version::SEEDED_METADATA),
+            None => self.version,
+        }
+    }
+
+    /// The metadata as written, including the custom ruleset
+    pub(crate) fn written_metadata(&self) -> Metadata {
+        let mut metadata = self.metadata.clone();
+        if let Some(ruleset) = &self.ruleset {
+            metadata.set(Metadata::RULES, &ruleset.canonical());
+        }
+        metadata
+    }
+
+    /// Take the custom ruleset written by [written_metadata](Self::written_metadata) out of `metadata`.
+    ///
+    /// The ruleset isn't validated here, replaying the recording does that.
+    pub(crate) fn read_ruleset(
+        metadata: &mut Metadata,
+    ) -> Result<Option<ConfigurableRuleset>, RulesetError> {
+        metadata
+            .0
+            .remove(Metadata::RULES)
+            .map(|spec| serde_json::from_str(&spec).map_err(|e| RulesetError::Parse(e.to_string())))
+            .transpose()
+    }
+
+    /// Check that the custom ruleset of the recording, if any, is valid
+    pub fn check_ruleset(&self) -> Result<(), RulesetError> {
+        match &self.ruleset {
+            Some(ruleset) => ruleset.validate(),
+            None => Ok(()),
+        }
+    }
+
     pub fn get_current_board(&self) -> Result<crate::board::Board, MoveReplayError> {
         let reconstruction = self.reconstruct()?;
         // We can unwrap here, replay_moves should always return a valid board
-        Ok(*reconstruction.history.last().unwrap())
+        Ok(reconstruction.history.last().unwrap().clone())
     }
 }
 
 impl Validatable for SeededRecording {
     type Error = MoveReplayError;
     fn validate(&self) -> Result<ValidationResult, Self::Error> {
+        if let Some(packed) = replay_moves_packed(self)? {
+            return Ok(packed.validation_data);
+        }
         let reconstruction = self.reconstruct()?;
         Ok(reconstruction.validation_data)
     }
@@ -71,10 +184,9 @@ impl Validatable for SeededRecording {
 
 impl RulesetProvider for SeededRecording {
     fn rules(&self) -> &dyn Ruleset {
-        match self.version {
-            1 => &ClassicV1,
-            2 => &ClassicV2,
-            _ => &ClassicV2, // we should probably panic here
+        match &self.ruleset {
+            Some(ruleset) => ruleset,
+            None => crate::rules::for_version(self.version),
         }
     }
 }
@@ -83,14 +195,22 @@ impl Hashable for SeededRecording {
     fn game_hash(&self) -> String {
         use sha2::{Digest, Sha256};
 
+        let version = self.written_version();
         let mut hasher = Sha256::new();
-        hasher.update(self.version.to_string().as_bytes());
+        hasher.update(version.to_string().as_bytes());
         hasher.update(self.seed.to_string().as_bytes());
         hasher.update(self.width.to_string().as_bytes());
         hasher.update(self.height.to_string().as_bytes());
         for i in &self.moves {
             hasher.update(i.get_index().to_string().as_bytes());
         }
+        if let Some(ruleset) = &self.ruleset {
+            hasher.update(ruleset.canonical().as_bytes());
+        }
+        if version >= version::SEEDED_METADATA {
+            hasher.update(self.metadata.encode().as_bytes());
+            return format!("V3{:X}", hasher.finalize());
+        }
         format!("V2{:X}", hasher.finalize())
     }
 }
@@ -98,9 +218,13 @@ impl Hashable for SeededRecording {
 #[cfg(test)]
 pub mod tests {
     use crate::{
-        unified::hash::Hashable,
-        v2::test_data::{
-            GAME_NI4FIRM, GAME_NI4FIRM_HASH, GAME_WON_3_BREAKS, GAME_WON_3_BREAKS_HASH,
+        rules::configurable::{ConfigurableRuleset, RulesetError},
+        unified::{hash::Hashable, validation::Validatable},
+        v2::{
+            replay::MoveReplayError,
+            test_data::{
+                GAME_NI4FIRM, GAME_NI4FIRM_HASH, GAME_WON_3_BREAKS, GAME_WON_3_BREAKS_HASH,
+            },
         },
     };
 
@@ -117,4 +241,70 @@ pub mod tests {
         let parsed: SeededRecording = GAME_WON_3_BREAKS.parse().unwrap();
         assert_eq!(parsed.game_hash(), GAME_WON_3_BREAKS_HASH);
     }
+
+    #[test]
+    fn custom_ruleset() {
+        let mut recording: SeededRecording = GAME_WON_3_BREAKS.parse().unwrap();
+        let expected = recording.validate().unwrap();
+
+        recording.ruleset = Some(ConfigurableRuleset::new("classic"));
+        assert_eq!(recording.validate().unwrap(), expected);
+        assert_ne!(recording.game_hash(), GAME_WON_3_BREAKS_HASH);
+
+        let json = serde_json::to_string(&recording).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SeededRecording>(&json).unwrap(),
+            recording
+        );
+
+        let mut strict = ConfigurableRuleset::new("no-breaks");
+        strict.break_max = 0;
+        recording.ruleset = Some(strict);
+        assert!(matches!(
+            recording.validate(),
+            Err(MoveReplayError::TooManyBreaks(_, 0, 0))
+        ));
+
+        let mut invalid = ConfigurableRuleset::new("invalid");
+        invalid.break_max = 4;
+        recording.ruleset = Some(invalid);
+        assert!(matches!(
+            recording.validate(),
+            Err(MoveReplayError::InvalidRuleset(
+                RulesetError::TooManyBreaks(4, 3)
+            ))
+        ));
+    }
+
+    #[test]
+    fn ruleset_is_hashed_and_carried() {
+        let recording: SeededRecording = GAME_WON_3_BREAKS.parse().unwrap();
+        let recording = recording.with_ruleset(ConfigurableRuleset::new("classic"));
+        let hash = recording.game_hash();
+
+        // A different spec under a trusted id doesn't get the same hash
+        let mut forged = recording.clone();
+        forged.ruleset.as_mut().unwrap().spawn_weights[1].weight = 100;
+        assert_ne!(forged.game_hash(), hash);
+
+        // The order of the break costs doesn't change what the ruleset does
+        let mut reordered = recording.clone();
+        reordered.ruleset.as_mut().unwrap().break_costs.reverse();
+        assert_eq!(reordered.game_hash(), hash);
+
+        let text: SeededRecording = String::from(&recording).parse().unwrap();
+        let binary = SeededRecording::from_bytes(&recording.to_bytes()).unwrap();
+        for parsed in [text, binary] {
+            assert_eq!(parsed, recording);
+            assert_eq!(parsed.game_hash(), hash);
+        }
+
+        // A v2 recording with a ruleset is written as v3, with the same hash
+        let mut v2: SeededRecording = GAME_WON_3_BREAKS.parse().unwrap();
+        v2.ruleset = recording.ruleset.clone();
+        let parsed: SeededRecording = String::from(&v2).parse().unwrap();
+        assert_eq!(parsed.version, 3);
+        assert_eq!(parsed.ruleset, v2.ruleset);
+        assert_eq!(parsed.game_hash(), v2.game_hash());
+    }
 }
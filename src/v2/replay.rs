@@ -6,10 +6,12 @@ use crate::rules::RulesetProvider;
 use crate::unified::reconstruction::{HistoryReconstruction, Reconstructable};
 use crate::unified::validation::{ValidationResult, MAX_ALLOWED_BREAKS};
 use crate::{
-    board::{Board, MoveError},
+    board::{
+        bitboard::{BitBoard, BitBoardError, MAX_EXACT_CELLS},
+        Board, MoveError,
+    },
     direction::Direction,
-    rules::Ruleset,
-    v1::validator::initialize_board,
+    rules::{configurable::RulesetError, spawn_tiles, Ruleset},
 };
 
 #[derive(Error, Debug, Clone, Serialize, Deserialize)]
@@ -22,51 +24,101 @@ pub enum MoveReplayError {
 
     #[error("can't break on move {0} as score {1} is't high enough (min {2})")]
     NotEnoughScoreToBreak(usize, usize, usize),
+
+    #[error("invalid ruleset: {0}")]
+    InvalidRuleset(#[from] RulesetError),
+
+    #[error("unknown random number generator `{0}`")]
+    UnknownRng(String),
+
+    #[error("unsupported version: {0}")]
+    UnsupportedVersion(u8),
 }
 
 /// Intended for reconstructing V2 format games
+///
+/// Replays on packed boards when possible, so the tile ids are numbered anew on every board and `merged_from` and `new`
+/// aren't tracked. Use [replay_moves_tracked] to keep track of the tiles between moves.
 pub fn replay_moves(recording: &SeededRecording) -> Result<HistoryReconstruction, MoveReplayError> {
+    match replay_moves_packed(recording)? {
+        Some(packed) => Ok(packed.materialize()),
+        None => replay_moves_tracked(recording),
+    }
+}
+
+/// The result of replaying a game on packed boards, see [replay_moves_packed]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedReconstruction {
+    pub validation_data: ValidationResult,
+    pub history: Vec<BitBoard>,
+}
+
+impl PackedReconstruction {
+    /// Convert the packed history into regular [Board]s, assigning tile ids on the way
+    pub fn materialize(&self) -> HistoryReconstruction {
+        HistoryReconstruction {
+            validation_data: self.validation_data,
+            history: self.history.iter().map(Board::from).collect(),
+        }
+    }
+}
+
+/// Replay a V2 game on [BitBoard]s.
+///
+/// Returns `Ok(None)` if the game can't be replayed with packed boards, e.g. if it is bigger than
+/// [MAX_EXACT_CELLS] or a tile grows past the packable maximum.
+pub fn replay_moves_packed(
+    recording: &SeededRecording,
+) -> Result<Option<PackedReconstruction>, MoveReplayError> {
+    recording.check_ruleset()?;
+    recording.check_rng()?;
+    if recording.width * recording.height > MAX_EXACT_CELLS {
+        return Ok(None);
+    }
     let rules = recording.rules();
     let mut score: usize = 0;
     let mut max_score: usize = 0;
 
-    let mut board = initialize_board(recording.width, recording.height, recording.seed, 2);
-    let mut history_out: Vec<Board> = vec![board];
+    let Ok(mut board) = BitBoard::new(recording.width, recording.height, recording.seed) else {
+        return Ok(None);
+    };
+    board.rng = recording.rng();
+    // The spawn rules only depend on the size of the board, so they're only looked up once
+    let unpacked = Board::from(&board);
+    let spawn_weights = rules.spawn_weights(&unpacked);
+    let spawns_per_move = rules.spawns_per_move(&unpacked);
+    for _ in 0..rules.initial_tiles(&unpacked) {
+        if board.add_random_tile_with(spawn_weights).is_err() {
+            return Ok(None);
+        }
+    }
+    let mut history_out: Vec<BitBoard> = vec![board];
 
     let mut breaks: usize = 0;
     let mut break_positions = [None; MAX_ALLOWED_BREAKS];
 
     for (move_index, mv) in recording.moves.iter().copied().enumerate() {
         if mv != Direction::BREAK {
-            let mvchk = crate::board::check_move(board, mv)
-                .map_err(|e| MoveReplayError::InvalidMove(mv, move_index, e))?;
-            board = mvchk.board;
-            score += mvchk.score_gain;
-            board.add_random_tile();
-        } else {
-            // check if a break is allowed
-            let max_breaks = rules.break_max(&board);
-            if breaks >= max_breaks {
-                return Err(MoveReplayError::TooManyBreaks(
-                    move_index, breaks, max_breaks,
-                ));
-            }
-            let cost = rules.break_cost(&board);
-            if score < cost {
-                return Err(MoveReplayError::NotEnoughScoreToBreak(
-                    move_index, score, cost,
-                ));
+            match board.check_move(mv) {
+                Ok((moved, score_gain)) => {
+                    board = moved;
+                    score += score_gain;
+                }
+                Err(BitBoardError::Move(e)) => {
+                    return Err(MoveReplayError::InvalidMove(mv, move_index, e))
+                }
+                Err(_) => return Ok(None),
             }
-            if rules.game_over(&board) {
-                return Err(MoveReplayError::InvalidMove(
-                    mv,
-                    move_index,
-                    MoveError::NoValidMovesLeft,
-                ));
+            for _ in 0..spawns_per_move {
+                if board.add_random_tile_with(spawn_weights).is_err() {
+                    return Ok(None);
+                }
             }
-            score -= cost;
+        } else {
+            let unpacked = Board::from(&board);
+            score -= check_break(rules, &unpacked, move_index, score, breaks)?;
             break_positions[breaks] = Some(move_index);
-            actuate_break(&mut board, rules);
+            board.clear_below(rules.break_tile_threshold(&unpacked));
             breaks += 1;
         }
 
@@ -74,7 +126,7 @@ pub fn replay_moves(recording: &SeededRecording) -> Result<HistoryReconstruction
         history_out.push(board);
     }
 
-    Ok(HistoryReconstruction {
+    Ok(Some(PackedReconstruction {
         validation_data: ValidationResult {
             score: max_score,
             score_end: score,
@@ -83,43 +135,177 @@ pub fn replay_moves(recording: &SeededRecording) -> Result<HistoryReconstruction
             break_positions,
         },
         history: history_out,
+    }))
+}
+
+/// Replay a V2 game with regular [Board]s, keeping track of the tile ids between moves
+pub fn replay_moves_tracked(
+    recording: &SeededRecording,
+) -> Result<HistoryReconstruction, MoveReplayError> {
+    let rules = recording.rules();
+    let mut state = ReplayState::new(recording)?;
+    let mut history_out: Vec<Board> = vec![state.board.clone()];
+
+    for mv in recording.moves.iter().copied() {
+        state.apply(rules, mv)?;
+        history_out.push(state.board.clone());
+    }
+
+    Ok(HistoryReconstruction {
+        validation_data: state.validation_data(),
+        history: history_out,
     })
 }
 
+/// The state of a V2 game after some amount of moves, used to replay a game one move at a time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ReplayState {
+    /// The current board
+    pub board: Board,
+    /// The current score
+    pub score: usize,
+    /// The maximum score reached so far
+    pub max_score: usize,
+    /// Amount of breaks used
+    pub breaks: usize,
+    /// When those breaks happened
+    pub break_positions: [Option<usize>; MAX_ALLOWED_BREAKS],
+    /// Amount of moves applied so far
+    pub moves: usize,
+}
+
+impl ReplayState {
+    /// Create the starting state of `recording` with its initial tiles added, ignoring its moves.
+    ///
+    /// Fails if the recording has an invalid ruleset or names an unknown random number generator.
+    pub fn new(recording: &SeededRecording) -> Result<ReplayState, MoveReplayError> {
+        recording.check_ruleset()?;
+        recording.check_rng()?;
+        let rules = recording.rules();
+        let mut board = Board::new_with_rng(
+            recording.width,
+            recording.height,
+            recording.seed,
+            recording.rng(),
+        );
+        let initial_tiles = rules.initial_tiles(&board);
+        spawn_tiles(rules, &mut board, initial_tiles);
+        Ok(ReplayState {
+            board,
+            score: 0,
+            max_score: 0,
+            breaks: 0,
+            break_positions: [None; MAX_ALLOWED_BREAKS],
+            moves: 0,
+        })
+    }
+
+    /// Apply the next move of the game. The state is left untouched if the move is not valid.
+    pub fn apply(&mut self, rules: &dyn Ruleset, mv: Direction) -> Result<(), MoveReplayError> {
+        let move_index = self.moves;
+        if mv != Direction::BREAK {
+            let mvchk = crate::board::check_move(self.board.clone(), mv)
+                .map_err(|e| MoveReplayError::InvalidMove(mv, move_index, e))?;
+            self.board = mvchk.board;
+            self.score += mvchk.score_gain;
+            let spawns = rules.spawns_per_move(&self.board);
+            spawn_tiles(rules, &mut self.board, spawns);
+        } else {
+            self.score -= check_break(rules, &self.board, move_index, self.score, self.breaks)?;
+            self.break_positions[self.breaks] = Some(move_index);
+            actuate_break(&mut self.board, rules);
+            self.breaks += 1;
+        }
+
+        self.max_score = usize::max(self.score, self.max_score);
+        self.moves += 1;
+        Ok(())
+    }
+
+    /// Get the validation data of the moves applied so far
+    pub fn validation_data(&self) -> ValidationResult {
+        ValidationResult {
+            score: self.max_score,
+            score_end: self.score,
+            score_margin: 0,
+            breaks: self.breaks,
+            break_positions: self.break_positions,
+        }
+    }
+}
+
+/// Check if a break is allowed and return its cost
+fn check_break(
+    rules: &dyn Ruleset,
+    board: &Board,
+    move_index: usize,
+    score: usize,
+    breaks: usize,
+) -> Result<usize, MoveReplayError> {
+    let max_breaks = crate::rules::break_max(rules, board);
+    if breaks >= max_breaks {
+        return Err(MoveReplayError::TooManyBreaks(
+            move_index, breaks, max_breaks,
+        ));
+    }
+    let cost = rules.break_cost(board);
+    if score < cost {
+        return Err(MoveReplayError::NotEnoughScoreToBreak(
+            move_index, score, cost,
+        ));
+    }
+    if rules.game_over(board) {
+        return Err(MoveReplayError::InvalidMove(
+            Direction::BREAK,
+            move_index,
+            MoveError::NoValidMovesLeft,
+        ));
+    }
+    Ok(cost)
+}
+
 fn actuate_break(board: &mut Board, rules: &dyn Ruleset) {
     let tile_threshold = rules.break_tile_threshold(board);
     // remove all tiles with value < tile_threshold
-    board.tiles = board.tiles.map(|c| {
-        c.map(|t| {
-            t.map(|t| {
-                if t.value >= tile_threshold {
-                    t
-                } else {
-                    crate::board::tile::Tile { value: 0, ..t }
-                }
-            })
-        })
-    });
+    for t in board.tiles.iter_mut().flatten().flatten() {
+        if t.value < tile_threshold {
+            t.value = 0;
+        }
+    }
 }
 
+/// Keeps track of the tiles between moves, see [replay_moves] for a faster replay without them
 impl Reconstructable for SeededRecording {
     type ReconstructionError = MoveReplayError;
     fn reconstruct(&self) -> Result<HistoryReconstruction, Self::ReconstructionError> {
-        replay_moves(self)
+        replay_moves_tracked(self)
     }
 }
 
 #[cfg(test)]
 pub mod tests {
+    use super::{replay_moves_packed, replay_moves_tracked, MoveReplayError, ReplayState};
     use crate::{
-        unified::game::GameState,
+        board::{check_move, tile::SpawnWeight, Board},
+        direction::MOVE_DIRECTIONS,
+        random::RandAlgo,
+        rules::configurable::ConfigurableRuleset,
+        unified::{
+            game::{Game, GameState},
+            reconstruction::Reconstructable,
+        },
+        v1::validator::initialize_board,
         v2::{
+            metadata::Metadata,
             recording::SeededRecording,
             test_data::{GAME_INVALID_BREAK_AFTER_LOSS, GAME_NI4FIRM, GAME_WON_3_BREAKS},
         },
     };
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn correctness_a() {
         let rec: SeededRecording = GAME_NI4FIRM.parse().unwrap();
         let state = GameState::from_reconstructable_ruleset(&rec).unwrap();
@@ -133,6 +319,7 @@ pub mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn correctness_b() {
         let rec: SeededRecording = GAME_WON_3_BREAKS.parse().unwrap();
         let state = GameState::from_reconstructable_ruleset(&rec).unwrap();
@@ -145,6 +332,210 @@ pub mod tests {
         assert_eq!(state.over, false);
     }
 
+    #[test]
+    fn reconstruct_keeps_tile_data() {
+        let rec: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        let history = rec.reconstruct().unwrap().history;
+        let tiles: Vec<_> = history
+            .iter()
+            .flat_map(|board| board.get_occupied_tiles())
+            .collect();
+        assert!(tiles.iter().any(|tile| tile.merged_from.is_some()));
+        assert!(tiles.iter().any(|tile| tile.new));
+        let ids = |board: &Board| {
+            board
+                .get_occupied_tiles()
+                .iter()
+                .map(|t| t.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            history.iter().map(ids).collect::<Vec<_>>(),
+            replay_moves_tracked(&rec)
+                .unwrap()
+                .history
+                .iter()
+                .map(ids)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn replay_moves_uses_packed_boards() {
+        let rec: SeededRecording = GAME_WON_3_BREAKS.parse().unwrap();
+        let fast = super::replay_moves(&rec).unwrap();
+        let tracked = replay_moves_tracked(&rec).unwrap();
+        assert_eq!(fast.validation_data, tracked.validation_data);
+        for (fast, tracked) in fast.history.iter().zip(&tracked.history) {
+            assert_eq!(fast.get_all_tiles(), tracked.get_all_tiles());
+            assert!(fast
+                .get_occupied_tiles()
+                .iter()
+                .all(|t| t.merged_from.is_none()));
+        }
+    }
+
+    #[test]
+    fn packed_matches_tracked() {
+        for game in [GAME_NI4FIRM, GAME_WON_3_BREAKS] {
+            let rec: SeededRecording = game.parse().unwrap();
+            let packed = replay_moves_packed(&rec).unwrap().unwrap();
+            let tracked = replay_moves_tracked(&rec).unwrap();
+            assert_eq!(packed.validation_data, tracked.validation_data);
+            assert_eq!(packed.history.len(), tracked.history.len());
+            for (packed, tracked) in packed.history.iter().zip(&tracked.history) {
+                assert_eq!(Board::from(packed).get_all_tiles(), tracked.get_all_tiles());
+                assert_eq!(packed.rng_state, tracked.rng_state);
+            }
+        }
+    }
+
+    #[test]
+    fn custom_spawn_rules() {
+        let mut ruleset = ConfigurableRuleset::new("fours");
+        ruleset.spawn_weights = vec![SpawnWeight {
+            value: 4,
+            weight: 1,
+        }];
+        ruleset.initial_tiles = 3;
+        ruleset.spawns_per_move = 2;
+        let mut rec = SeededRecording::empty(1234, 4, 4);
+        rec.ruleset = Some(ruleset);
+
+        let mut game = Game::from_recording(&rec).unwrap();
+        let initial = game.board().get_occupied_tiles();
+        assert_eq!(initial.len(), 3);
+        assert!(initial.iter().all(|t| t.value == 4));
+        while let Some(dir) = MOVE_DIRECTIONS
+            .into_iter()
+            .find(|dir| check_move(game.board().clone(), *dir).is_ok())
+        {
+            game.play(dir).unwrap();
+        }
+        let rec = game.recording();
+        assert!(!rec.moves.is_empty());
+
+        let packed = replay_moves_packed(rec).unwrap().unwrap();
+        let tracked = replay_moves_tracked(rec).unwrap();
+        assert_eq!(packed.validation_data, tracked.validation_data);
+        for (packed, tracked) in packed.history.iter().zip(&tracked.history) {
+            assert_eq!(Board::from(packed).get_all_tiles(), tracked.get_all_tiles());
+        }
+        let tiles = tracked.history[1].get_occupied_tiles();
+        assert_eq!(
+            tiles.len(),
+            5 - tiles.iter().filter(|t| t.value == 8).count()
+        );
+    }
+
+    #[test]
+    fn packed_rejects_invalid_break() {
+        let rec: SeededRecording = GAME_INVALID_BREAK_AFTER_LOSS.parse().unwrap();
+        assert!(replay_moves_packed(&rec).is_err());
+        assert!(replay_moves_tracked(&rec).is_err());
+    }
+
+    #[test]
+    fn packed_skips_big_boards() {
+        let rec = SeededRecording::new(0, 6, 6, vec![crate::direction::Direction::UP]);
+        assert_eq!(replay_moves_packed(&rec).unwrap(), None);
+        assert!(super::replay_moves(&rec).is_ok());
+    }
+
+    #[test]
+    fn replays_big_boards() {
+        for size in [7, 8, 10] {
+            let mut board = initialize_board(size, size, 1234, 2);
+            let mut moves = vec![];
+            for _ in 0..100 {
+                let Some(dir) = MOVE_DIRECTIONS
+                    .into_iter()
+                    .find(|dir| check_move(board.clone(), *dir).is_ok())
+                else {
+                    break;
+                };
+                board.move_in_direction(dir).unwrap();
+                board.add_random_tile();
+                moves.push(dir);
+            }
+            let rec = SeededRecording::new(1234, size, size, moves);
+            let state = GameState::from_reconstructable_ruleset(&rec).unwrap();
+            assert_eq!(state.board.width, size);
+            assert_eq!(state.board.height, size);
+            assert_eq!(state.board.get_all_tiles(), board.get_all_tiles());
+        }
+    }
+
+    #[test]
+    fn replays_with_own_rng() {
+        for rng in [RandAlgo::Xorshift32, RandAlgo::Pcg32, RandAlgo::SplitMix32] {
+            let mut board = Board::new_with_rng(4, 4, 1234, rng);
+            board.add_random_tile();
+            board.add_random_tile();
+            let mut moves = vec![];
+            for _ in 0..200 {
+                let Some(dir) = MOVE_DIRECTIONS
+                    .into_iter()
+                    .find(|dir| check_move(board.clone(), *dir).is_ok())
+                else {
+                    break;
+                };
+                board.move_in_direction(dir).unwrap();
+                board.add_random_tile();
+                moves.push(dir);
+            }
+            let rec = SeededRecording::new(1234, 4, 4, moves).with_rng(rng);
+            let rec: SeededRecording = String::from(&rec).parse().unwrap();
+            assert_eq!(rec.rng(), rng);
+            for replayed in [
+                replay_moves_tracked(&rec).unwrap().history,
+                replay_moves_packed(&rec)
+                    .unwrap()
+                    .unwrap()
+                    .materialize()
+                    .history,
+            ] {
+                assert_eq!(
+                    replayed.last().unwrap().get_all_tiles(),
+                    board.get_all_tiles()
+                );
+            }
+
+            // The default generator spawns different tiles for the same seed
+            let lcg = SeededRecording::new(1234, 4, 4, rec.moves.clone());
+            assert_eq!(lcg.rng(), RandAlgo::LCG);
+            let lcg_start = &ReplayState::new(&lcg).unwrap().board;
+            assert_ne!(
+                lcg_start.get_all_tiles(),
+                ReplayState::new(&rec).unwrap().board.get_all_tiles()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_rng() {
+        let mut rec = SeededRecording::empty(1234, 4, 4).with_rng(RandAlgo::Pcg32);
+        rec.metadata.set(Metadata::RNG, "mersenne");
+        assert!(matches!(
+            replay_moves_tracked(&rec),
+            Err(MoveReplayError::UnknownRng(_))
+        ));
+        assert!(matches!(
+            replay_moves_packed(&rec),
+            Err(MoveReplayError::UnknownRng(_))
+        ));
+
+        // Only version 3 carries metadata, older recordings use the generator of their version
+        rec.version = crate::version::SEEDED;
+        assert_eq!(rec.rng(), RandAlgo::LCG);
+        rec.version = crate::version::EXPLICIT;
+        rec.metadata = Metadata::default();
+        assert!(matches!(
+            replay_moves_tracked(&rec),
+            Err(MoveReplayError::UnsupportedVersion(4))
+        ));
+    }
+
     #[test]
     #[should_panic]
     fn correctness_c() {
@@ -0,0 +1,214 @@
+//! Provides [StreamValidator] to validate a game while it's being played, one move at a time.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    recording::SeededRecording,
+    replay::{MoveReplayError, ReplayState},
+};
+use crate::{
+    board::Board,
+    direction::Direction,
+    rules::{configurable::ConfigurableRuleset, Ruleset, RulesetProvider},
+    unified::validation::ValidationResult,
+};
+
+/// Validates a [SeededRecording] as a stream of moves.
+///
+/// Every move is checked against the current state as it arrives, so validating a whole game costs the same as replaying it once.
+/// The validator can be serialized between moves and resumed later without replaying the moves again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct StreamValidator {
+    pub version: u8,
+    pub seed: u32,
+    pub width: usize,
+    pub height: usize,
+    /// The custom ruleset of the recording, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ruleset: Option<ConfigurableRuleset>,
+    state: ReplayState,
+}
+
+/// The outcome of a move that was accepted by a [StreamValidator]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct MoveVerdict {
+    /// Index of the move in the recording
+    pub move_index: usize,
+    /// The score after the move
+    pub score: usize,
+    /// The maximum score reached so far
+    pub score_max: usize,
+    /// Amount of breaks used so far
+    pub breaks: usize,
+    /// The board after the move, including the tile added after it
+    pub board: Board,
+}
+
+impl StreamValidator {
+    /// Start validating a new game
+    pub fn new(seed: u32, width: usize, height: usize) -> Self {
+        Self::from_header(&SeededRecording::empty(seed, width, height))
+            .expect("an empty recording is always valid")
+    }
+
+    /// Start validating a game with the version, seed, size, ruleset and generator of `recording`, ignoring its moves
+    pub fn from_header(recording: &SeededRecording) -> Result<Self, MoveReplayError> {
+        Ok(Self {
+            version: recording.version,
+            seed: recording.seed,
+            width: recording.width,
+            height: recording.height,
+            ruleset: recording.ruleset.clone(),
+            state: ReplayState::new(recording)?,
+        })
+    }
+
+    /// Start validating a game with the moves of `recording` already validated
+    pub fn from_recording(recording: &SeededRecording) -> Result<Self, MoveReplayError> {
+        let mut validator = Self::from_header(recording)?;
+        for mv in recording.moves.iter().copied() {
+            validator.push(mv)?;
+        }
+        Ok(validator)
+    }
+
+    /// Validate the next move.
+    ///
+    /// A rejected move is not applied, the validator stays in the state it was in before the move.
+    pub fn push(&mut self, mv: Direction) -> Result<MoveVerdict, MoveReplayError> {
+        let move_index = self.state.moves;
+        let rules: &dyn Ruleset = match &self.ruleset {
+            Some(ruleset) => {
+                ruleset.validate()?;
+                ruleset
+            }
+            None => crate::rules::for_version(self.version),
+        };
+        self.state.apply(rules, mv)?;
+        Ok(MoveVerdict {
+            move_index,
+            score: self.state.score,
+            score_max: self.state.max_score,
+            breaks: self.state.breaks,
+            board: self.state.board.clone(),
+        })
+    }
+
+    /// Amount of moves validated so far
+    pub fn len(&self) -> usize {
+        self.state.moves
+    }
+
+    /// True if no moves have been validated yet
+    pub fn is_empty(&self) -> bool {
+        self.state.moves == 0
+    }
+
+    /// The current board
+    pub fn board(&self) -> &Board {
+        &self.state.board
+    }
+
+    /// The validation result of the moves validated so far
+    pub fn validation_data(&self) -> ValidationResult {
+        self.state.validation_data()
+    }
+}
+
+impl RulesetProvider for StreamValidator {
+    fn rules(&self) -> &dyn Ruleset {
+        match &self.ruleset {
+            Some(ruleset) => ruleset,
+            None => crate::rules::for_version(self.version),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        random::RandAlgo,
+        unified::validation::Validatable,
+        v2::{
+            metadata::Metadata,
+            test_data::{GAME_INVALID_BREAK_AFTER_LOSS, GAME_NI4FIRM, GAME_WON_3_BREAKS},
+        },
+    };
+
+    #[test]
+    fn matches_validate() {
+        for data in [GAME_NI4FIRM, GAME_WON_3_BREAKS] {
+            let recording: SeededRecording = data.parse().unwrap();
+            let mut validator = StreamValidator::from_header(&recording).unwrap();
+            for (index, mv) in recording.moves.iter().enumerate() {
+                let verdict = validator.push(*mv).unwrap();
+                assert_eq!(verdict.move_index, index);
+            }
+            assert_eq!(validator.len(), recording.moves.len());
+            assert_eq!(validator.validation_data(), recording.validate().unwrap());
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_moves() {
+        let recording: SeededRecording = GAME_INVALID_BREAK_AFTER_LOSS.parse().unwrap();
+        let expected = recording.validate().unwrap_err();
+        let mut validator = StreamValidator::from_header(&recording).unwrap();
+        for mv in recording.moves.iter().copied() {
+            if let Err(e) = validator.push(mv) {
+                assert_eq!(e.to_string(), expected.to_string());
+                return;
+            }
+        }
+        panic!("the recording should've been rejected");
+    }
+
+    #[test]
+    fn rejected_moves_are_not_applied() {
+        let mut validator = StreamValidator::new(0, 4, 4);
+        let board = validator.board().clone();
+        assert!(validator.push(Direction::BREAK).is_err());
+        assert!(validator.is_empty());
+        assert_eq!(validator.board(), &board);
+    }
+
+    #[test]
+    fn resume() {
+        let recording: SeededRecording = GAME_WON_3_BREAKS.parse().unwrap();
+        let (first, second) = recording.moves.split_at(recording.moves.len() / 2);
+        let mut validator = StreamValidator::from_header(&recording).unwrap();
+        for mv in first {
+            validator.push(*mv).unwrap();
+        }
+        let serialized = serde_json::to_string(&validator).unwrap();
+        let mut resumed: StreamValidator = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(resumed, validator);
+        for mv in second {
+            resumed.push(*mv).unwrap();
+        }
+        assert_eq!(resumed.validation_data(), recording.validate().unwrap());
+    }
+
+    #[test]
+    fn rejects_invalid_header() {
+        let mut recording = SeededRecording::empty(0, 4, 4).with_rng(RandAlgo::Pcg32);
+        recording.metadata.set(Metadata::RNG, "mersenne");
+        assert!(matches!(
+            StreamValidator::from_header(&recording),
+            Err(MoveReplayError::UnknownRng(_))
+        ));
+
+        let mut ruleset = ConfigurableRuleset::new("endless");
+        ruleset.spawns_per_move = usize::MAX;
+        let recording = SeededRecording::empty(0, 4, 4).with_ruleset(ruleset);
+        assert!(matches!(
+            StreamValidator::from_header(&recording),
+            Err(MoveReplayError::InvalidRuleset(_))
+        ));
+    }
+}
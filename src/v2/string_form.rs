@@ -0,0 +1,110 @@
+//! Serde adapter for serializing a [SeededRecording] as its text format instead of a struct.
+//!
+//! The struct form lists every move separately, which makes recordings embedded in JSON many times larger than the
+//! text format. Use the adapter on a field with `#[serde(with = "...")]`:
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use twothousand_forty_eight::v2::recording::SeededRecording;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Submission {
+//!     #[serde(with = "twothousand_forty_eight::v2::string_form")]
+//!     recording: SeededRecording,
+//! }
+//!
+//! let json = r#"{"recording":"::2:4:4:123:"}"#;
+//! let submission: Submission = serde_json::from_str(json).unwrap();
+//! assert_eq!(submission.recording.seed, 123);
+//! assert_eq!(serde_json::to_string(&submission).unwrap(), json);
+//! ```
+//!
+//! Both the text format and the struct form are accepted on input.
+//!
+//! [ParseResult](crate::unified::ParseResult) has the same adapter in [unified::string_form](crate::unified::string_form).
+
+use std::{fmt::Display, marker::PhantomData, str::FromStr};
+
+use serde::{
+    de::{self, value::MapAccessDeserializer, MapAccess, Visitor},
+    Deserialize, Deserializer, Serializer,
+};
+
+use super::recording::SeededRecording;
+
+pub fn serialize<S: Serializer>(
+    recording: &SeededRecording,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&String::from(recording))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<SeededRecording, D::Error> {
+    deserializer.deserialize_any(StringOrStruct(PhantomData))
+}
+
+/// Deserializes a `T` from either a string parsed with [FromStr] or the serde representation of `T`
+pub(crate) struct StringOrStruct<T>(pub(crate) PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for StringOrStruct<T>
+where
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a recording as a string or a map")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        value.parse().map_err(E::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+        T::deserialize(MapAccessDeserializer::new(map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::v2::{recording::SeededRecording, test_data::GAME_NI4FIRM};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper {
+        #[serde(with = "super")]
+        recording: SeededRecording,
+    }
+
+    #[test]
+    fn string_form() {
+        let recording: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        let wrapper = Wrapper {
+            recording: recording.clone(),
+        };
+        let json = serde_json::to_value(&wrapper).unwrap();
+        assert_eq!(json["recording"], String::from(&recording));
+        assert_eq!(serde_json::from_value::<Wrapper>(json).unwrap(), wrapper);
+    }
+
+    #[test]
+    fn accepts_struct_form() {
+        let recording: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        let json = serde_json::json!({ "recording": recording });
+        assert_eq!(
+            serde_json::from_value::<Wrapper>(json).unwrap().recording,
+            recording
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let error = serde_json::from_str::<Wrapper>(r#"{"recording":"::2:garbage"}"#).unwrap_err();
+        assert!(error.to_string().contains("invalid width"));
+        assert!(serde_json::from_str::<Wrapper>(r#"{"recording":5}"#).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Per-move timing data of a [SeededRecording] and a [TimingPolicy] to check it against.
+//!
+//! Each entry of [SeededRecording::timings] is the time in milliseconds between the move and the one before it,
+//! the first entry being measured from the start of the game.
+//!
+//! In the text format the timings follow the moves, separated by a `.`, as base64 encoded LEB128 varints.
+//! Most human moves take less than 16 seconds, so they fit in two bytes.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    recording::SeededRecording,
+    varint::{self, VarintError},
+};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TimingParseError {
+    #[error("invalid base64 in timings")]
+    InvalidEncoding,
+    #[error("timing entry {0} doesn't fit into 32 bits")]
+    Overflow(usize),
+    #[error("timing entry {0} is cut short")]
+    Truncated(usize),
+}
+
+/// Encode the timings for the text format
+pub fn encode(timings: &[u32]) -> String {
+    varint::encode(timings.iter().map(|&timing| timing as u64))
+}
+
+/// Decode timings written by [encode]
+pub fn decode(data: &str) -> Result<Vec<u32>, TimingParseError> {
+    let values = varint::decode(data).map_err(|e| match e {
+        VarintError::InvalidEncoding => TimingParseError::InvalidEncoding,
+        VarintError::Overflow(index) => TimingParseError::Overflow(index),
+        VarintError::Truncated(index) => TimingParseError::Truncated(index),
+    })?;
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| u32::try_from(value).map_err(|_| TimingParseError::Overflow(index)))
+        .collect()
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TimingViolation {
+    #[error("the recording has no timing data")]
+    Missing,
+    #[error("the recording has {0} timing entries for {1} moves")]
+    LengthMismatch(usize, usize),
+    #[error("{0} moves were played faster than allowed, at most {1} are allowed")]
+    TooManyFastMoves(usize, usize),
+    #[error(
+        "{moves} moves were played in {duration}ms ending at move {index}, the minimum is {min}ms"
+    )]
+    SustainedRate {
+        index: usize,
+        moves: usize,
+        duration: u64,
+        min: u64,
+    },
+}
+
+/// Limits on how fast a game may be played.
+///
+/// The defaults are lenient enough for fast human players while catching bots that don't throttle themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "wasm", derive(tsify::Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(default)]
+pub struct TimingPolicy {
+    /// Whether a recording without timings is a violation
+    pub require_timings: bool,
+    /// Moves taking less than this many milliseconds are considered fast
+    pub min_interval: u32,
+    /// How many fast moves are allowed in total
+    pub max_fast_moves: usize,
+    /// The number of consecutive moves [TimingPolicy::window_min_duration] applies to, 0 to disable the check
+    pub window: usize,
+    /// The minimum number of milliseconds any [TimingPolicy::window] consecutive moves may take
+    pub window_min_duration: u64,
+}
+
+impl Default for TimingPolicy {
+    fn default() -> Self {
+        Self {
+            require_timings: false,
+            min_interval: 40,
+            max_fast_moves: 10,
+            window: 20,
+            window_min_duration: 2000,
+        }
+    }
+}
+
+impl TimingPolicy {
+    /// Check the timings of the recording, returning the first violation found
+    pub fn check(&self, recording: &SeededRecording) -> Result<(), TimingViolation> {
+        let Some(timings) = &recording.timings else {
+            return match self.require_timings {
+                true => Err(TimingViolation::Missing),
+                false => Ok(()),
+            };
+        };
+        if timings.len() != recording.moves.len() {
+            return Err(TimingViolation::LengthMismatch(
+                timings.len(),
+                recording.moves.len(),
+            ));
+        }
+
+        let fast = timings.iter().filter(|&&t| t < self.min_interval).count();
+        if fast > self.max_fast_moves {
+            return Err(TimingViolation::TooManyFastMoves(fast, self.max_fast_moves));
+        }
+
+        if self.window > 0 && timings.len() >= self.window {
+            let mut duration: u64 = timings[..self.window].iter().map(|&t| t as u64).sum();
+            for index in self.window - 1..timings.len() {
+                if index >= self.window {
+                    duration += timings[index] as u64;
+                    duration -= timings[index - self.window] as u64;
+                }
+                if duration < self.window_min_duration {
+                    return Err(TimingViolation::SustainedRate {
+                        index,
+                        moves: self.window,
+                        duration,
+                        min: self.window_min_duration,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::test_data::GAME_NI4FIRM;
+    use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+
+    #[test]
+    fn round_trip() {
+        let timings = vec![0, 1, 127, 128, 250, 16_383, 16_384, u32::MAX];
+        let encoded = encode(&timings);
+        assert!(!encoded.contains(':') && !encoded.contains('.'));
+        assert_eq!(decode(&encoded).unwrap(), timings);
+        assert_eq!(decode("").unwrap(), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(decode("!"), Err(TimingParseError::InvalidEncoding));
+        assert_eq!(
+            decode(&STANDARD_NO_PAD.encode([0x01, 0x80])),
+            Err(TimingParseError::Truncated(1))
+        );
+        assert_eq!(
+            decode(&STANDARD_NO_PAD.encode([0xFF, 0xFF, 0xFF, 0xFF, 0x7F])),
+            Err(TimingParseError::Overflow(0))
+        );
+    }
+
+    #[test]
+    fn policy() {
+        let mut recording: SeededRecording = GAME_NI4FIRM.parse().unwrap();
+        let moves = recording.moves.len();
+        let policy = TimingPolicy::default();
+        assert_eq!(policy.check(&recording), Ok(()));
+        let strict = TimingPolicy {
+            require_timings: true,
+            ..policy
+        };
+        assert_eq!(strict.check(&recording), Err(TimingViolation::Missing));
+
+        recording.timings = Some(vec![250; moves]);
+        assert_eq!(strict.check(&recording), Ok(()));
+
+        recording.timings = Some(vec![250; moves - 1]);
+        assert_eq!(
+            policy.check(&recording),
+            Err(TimingViolation::LengthMismatch(moves - 1, moves))
+        );
+
+        recording.timings = Some(vec![25; moves]);
+        assert_eq!(
+            policy.check(&recording),
+            Err(TimingViolation::TooManyFastMoves(moves, 10))
+        );
+
+        // Steady enough to avoid the fast move limit, but too quick over a window
+        let mut timings = vec![250; moves];
+        timings[30..50].fill(50);
+        recording.timings = Some(timings);
+        assert_eq!(
+            policy.check(&recording),
+            Err(TimingViolation::SustainedRate {
+                index: 45,
+                moves: 20,
+                duration: 1800,
+                min: 2000,
+            })
+        );
+    }
+}
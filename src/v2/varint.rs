@@ -0,0 +1,131 @@
+//! LEB128 varints, used by the compact parts of the text formats in base64 and by the [binary](super::binary) encoding as is.
+
+use base64::Engine;
+use thiserror::Error;
+
+use crate::encoding::get_b64_engine;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum VarintError {
+    #[error("invalid base64")]
+    InvalidEncoding,
+    #[error("entry {0} doesn't fit into 64 bits")]
+    Overflow(usize),
+    #[error("entry {0} is cut short")]
+    Truncated(usize),
+}
+
+/// Encode the values as varints, in base64
+pub fn encode(values: impl IntoIterator<Item = u64>) -> String {
+    let mut bytes = vec![];
+    for value in values {
+        write(&mut bytes, value);
+    }
+    get_b64_engine().encode(bytes)
+}
+
+/// Decode values written by [encode]
+pub fn decode(data: &str) -> Result<Vec<u64>, VarintError> {
+    let bytes = get_b64_engine()
+        .decode(data)
+        .map_err(|_| VarintError::InvalidEncoding)?;
+    Reader::new(&bytes).collect()
+}
+
+/// Append a value to the bytes as a varint
+pub fn write(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Reads varints from bytes, along with any raw bytes between them
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// How many varints have been read, for the errors
+    count: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            count: 0,
+        }
+    }
+
+    /// How many bytes have been read
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Read the next `length` bytes as is, None if there aren't as many left
+    pub fn bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position.checked_add(length)?)?;
+        self.position += length;
+        Some(bytes)
+    }
+}
+
+impl Iterator for Reader<'_> {
+    type Item = Result<u64, VarintError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.bytes.len() {
+            return None;
+        }
+        let index = self.count;
+        self.count += 1;
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        while let Some(&byte) = self.bytes.get(self.position) {
+            self.position += 1;
+            let bits = (byte & 0x7F) as u64;
+            if shift > 63 || (shift > 0 && bits >> (64 - shift) != 0) {
+                self.position = self.bytes.len();
+                return Some(Err(VarintError::Overflow(index)));
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Some(Ok(value));
+            }
+            shift += 7;
+        }
+        Some(Err(VarintError::Truncated(index)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let values = vec![0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+        assert_eq!(decode(&encode(values.clone())), Ok(values));
+        assert_eq!(encode([]), "");
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(decode("!"), Err(VarintError::InvalidEncoding));
+        assert_eq!(
+            decode(&get_b64_engine().encode([1, 0x80])),
+            Err(VarintError::Truncated(1))
+        );
+        assert_eq!(
+            decode(&get_b64_engine().encode([0xFF; 11])),
+            Err(VarintError::Overflow(0))
+        );
+    }
+}
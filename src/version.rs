@@ -0,0 +1,13 @@
+//! The version numbers of the recording formats.
+//!
+//! Every format is told apart by its version, see [detect_version](crate::unified::detect_version), so the numbers
+//! are shared between all of them. A new format or version takes the next free number here.
+
+/// The original format of [Recording](crate::v1::recording::Recording), which has no version in the text
+pub const V1: u8 = 1;
+/// [SeededRecording](crate::v2::recording::SeededRecording)
+pub const SEEDED: u8 = 2;
+/// [SeededRecording](crate::v2::recording::SeededRecording) with [Metadata](crate::v2::metadata::Metadata)
+pub const SEEDED_METADATA: u8 = 3;
+/// [ExplicitRecording](crate::v2::explicit::ExplicitRecording)
+pub const EXPLICIT: u8 = 4;
@@ -7,17 +7,25 @@ use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
 use crate::{
-    board::Board,
+    board::{
+        trace::{check_move_traced, MoveEvent, TracedMoveResult},
+        Board,
+    },
     direction::Direction,
     unified::{
+        attestation::{SeedAttestation, SubmissionAttestation, VerifyingKey},
+        commitment::IssuedSeed,
         game::GameState,
         hash::Hashable,
         reconstruction::{HistoryReconstruction, Reconstructable},
         validation::ValidationResult,
         ParseResult,
     },
-    v1::{recording::Recording, validator::initialize_board},
-    v2::recording::SeededRecording,
+    v1::{
+        recording::Recording,
+        validator::{initialize_board, MoveDiagnostic, ValidationError},
+    },
+    v2::{recording::SeededRecording, stream::StreamValidator, timing::TimingPolicy},
     *,
 };
 
@@ -32,10 +40,7 @@ pub fn deserialize(data: &str) -> Result<ParseResult, JsValue> {
 
 #[wasm_bindgen]
 pub fn serialize(data: ParseResult) -> Result<String, JsValue> {
-    match data {
-        ParseResult::V1(rec) => Ok(format!("{}", rec)),
-        ParseResult::V2(sedrec) => Ok(String::from(&sedrec)),
-    }
+    Ok(String::from(&data))
 }
 
 #[wasm_bindgen]
@@ -48,6 +53,7 @@ pub fn reconstruct_recording(data: ParseResult) -> Result<HistoryReconstruction,
     match data {
         ParseResult::V1(rec) => Ok(rec.reconstruct().map_err(err_str)?),
         ParseResult::V2(sedrec) => Ok(sedrec.reconstruct().map_err(err_str)?),
+        ParseResult::Explicit(rec) => Ok(rec.reconstruct().map_err(err_str)?),
     }
 }
 
@@ -56,6 +62,39 @@ pub fn validate(data: &str) -> Result<ValidationResult, JsValue> {
     unified::validate(data).map_err(err_str)
 }
 
+/// Thrown by [validate_detailed]
+#[derive(Debug, Clone, Serialize, Deserialize, tsify::Tsify)]
+pub struct ValidationFailure {
+    pub message: String,
+    /// Details of the move that went wrong, if the recording is a v1 recording with an invalid move
+    pub diagnostic: Option<MoveDiagnostic>,
+}
+
+/// Like [validate], but throws a [ValidationFailure] instead of the message
+#[wasm_bindgen]
+pub fn validate_detailed(data: &str) -> Result<ValidationResult, JsValue> {
+    match unified::validate(data) {
+        Ok(result) => Ok(result),
+        Err(e) => {
+            let diagnostic = match e.downcast_ref::<ValidationError>() {
+                Some(ValidationError::InvalidScore(diagnostic)) => Some(*diagnostic.clone()),
+                _ => None,
+            };
+            let failure = ValidationFailure {
+                message: e.to_string(),
+                diagnostic,
+            };
+            Err(serde_wasm_bindgen::to_value(&failure)?)
+        }
+    }
+}
+
+/// Validate every line as a separate recording
+#[wasm_bindgen]
+pub fn validate_batch(data: &str) -> unified::batch::BatchReport {
+    unified::batch::validate_batch(data.lines())
+}
+
 #[tsify::declare]
 type ValidationResultOrError = Result<ValidationResult, String>;
 
@@ -70,40 +109,27 @@ pub fn validate_all(data: &str) -> String {
             .map(|parsed| {
                 let mut results = Vec::new();
                 match parsed {
-                    ParseResult::V1(rec) => {
-                        let mut moves_until_now = Vec::new();
-                        for frame in rec.history {
-                            let history_until_now = Recording {
-                                width: rec.width,
-                                height: rec.height,
-                                history: moves_until_now.clone(),
-                            };
-                            results.push(
-                                match unified::validate(&format!("{}", history_until_now)) {
-                                    Ok(result) => ValidationResultOrError::Ok(result),
-                                    Err(e) => ValidationResultOrError::Err(e.to_string()),
-                                },
-                            );
-                            moves_until_now.push(frame);
-                        }
+                    ParseResult::V1(rec) => results.extend(validate_prefixes(rec)),
+                    // Explicit recordings are validated like the v1 recordings they stand for
+                    ParseResult::Explicit(rec) => {
+                        results.extend(validate_prefixes(rec.to_recording()))
                     }
                     ParseResult::V2(sedrec) => {
-                        let mut moves_until_now = Vec::new();
+                        // Validate the moves as a stream instead of validating every prefix from scratch
+                        // Once the header or a move is rejected, every later prefix is too
+                        let mut validator = StreamValidator::from_header(&sedrec);
                         for frame in sedrec.moves {
-                            let history_until_now = SeededRecording {
-                                version: sedrec.version,
-                                width: sedrec.width,
-                                height: sedrec.height,
-                                seed: sedrec.seed,
-                                moves: moves_until_now.clone(),
-                            };
-                            results.push(
-                                match unified::validate(&String::from(&history_until_now)) {
-                                    Ok(result) => ValidationResultOrError::Ok(result),
-                                    Err(e) => ValidationResultOrError::Err(e.to_string()),
-                                },
-                            );
-                            moves_until_now.push(frame);
+                            results.push(match &validator {
+                                Ok(validator) => {
+                                    ValidationResultOrError::Ok(validator.validation_data())
+                                }
+                                Err(e) => ValidationResultOrError::Err(e.to_string()),
+                            });
+                            if let Ok(current) = &mut validator {
+                                if let Err(e) = current.push(frame) {
+                                    validator = Err(e);
+                                }
+                            }
                         }
                     }
                 }
@@ -116,13 +142,33 @@ pub fn validate_all(data: &str) -> String {
     serde_json::to_string(&result).unwrap()
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Validate every prefix of a v1 recording from scratch
+fn validate_prefixes(rec: Recording) -> Vec<ValidationResultOrError> {
+    let mut results = Vec::new();
+    let mut moves_until_now = Vec::new();
+    for frame in rec.history {
+        let history_until_now = Recording {
+            width: rec.width,
+            height: rec.height,
+            history: moves_until_now.clone(),
+        };
+        results.push(match unified::validate(&format!("{}", history_until_now)) {
+            Ok(result) => ValidationResultOrError::Ok(result),
+            Err(e) => ValidationResultOrError::Err(e.to_string()),
+        });
+        moves_until_now.push(frame);
+    }
+    results
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg(feature = "wasm")]
 #[derive(tsify::Tsify)]
 #[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
 pub struct MoveResult {
     board: Board,
     score_gain: usize,
+    events: Vec<MoveEvent>,
 }
 
 #[wasm_bindgen]
@@ -150,20 +196,28 @@ pub fn get_gamestate_from_recording(data: ParseResult) -> Result<GameState, JsVa
         ParseResult::V2(sedrec) => {
             GameState::from_reconstructable_ruleset(&sedrec).context("failed to get gamestate")
         }
+        ParseResult::Explicit(rec) => {
+            GameState::from_reconstructable_ruleset(&rec).context("failed to get gamestate")
+        }
     }
     .map_err(err_str)
 }
 
 #[wasm_bindgen]
 pub fn apply_move(board: Board, dir: Direction, add_random: bool) -> Result<MoveResult, JsValue> {
-    let mut board: Board = board;
-    let result = board.move_in_direction(dir);
-    if result.is_ok() && add_random {
-        board.add_random_tile();
+    let TracedMoveResult {
+        mut board,
+        score_gain,
+        mut events,
+    } = check_move_traced(board, dir).map_err(err_str)?;
+    if add_random {
+        events.extend(board.add_random_tile().as_ref().map(MoveEvent::spawn));
     }
-    result
-        .map(|score_gain| MoveResult { board, score_gain })
-        .map_err(err_str)
+    Ok(MoveResult {
+        board,
+        score_gain,
+        events,
+    })
 }
 
 #[wasm_bindgen]
@@ -178,14 +232,128 @@ pub fn hash(data: &str) -> Result<String, JsValue> {
     unified::hash(data).map_err(err_str)
 }
 
+/// The [position hash](unified::hash::PositionHashable), equal for the same game in every version
+#[wasm_bindgen]
+pub fn position_hash(data: &str) -> Result<String, JsValue> {
+    unified::position_hash(data).map_err(err_str)
+}
+
 #[wasm_bindgen]
 pub fn hash_recording(data: ParseResult) -> String {
     match data {
         ParseResult::V1(rec) => rec.game_hash(),
         ParseResult::V2(sedrec) => sedrec.game_hash(),
+        ParseResult::Explicit(rec) => rec.game_hash(),
     }
 }
 
+/// Encode a seeded recording in the [binary](v2::binary) format
+#[wasm_bindgen]
+pub fn to_bytes(data: &str) -> Result<Vec<u8>, JsValue> {
+    let recording: SeededRecording = data.parse().map_err(err_str)?;
+    Ok(recording.to_bytes())
+}
+
+/// Decode a seeded recording from the [binary](v2::binary) format into the text format
+#[wasm_bindgen]
+pub fn from_bytes(bytes: &[u8]) -> Result<String, JsValue> {
+    let recording = SeededRecording::from_bytes(bytes).map_err(err_str)?;
+    Ok(String::from(&recording))
+}
+
+/// Signing takes the secret key, so it's only exported with the `wasm-signing` feature, for servers running the
+/// bindings. A build shipped to players must leave it off.
+#[cfg(feature = "wasm-signing")]
+#[wasm_bindgen]
+pub fn sign_seed(
+    key: unified::attestation::SigningKey,
+    id: &str,
+    seed: u32,
+    width: usize,
+    height: usize,
+) -> Result<SeedAttestation, JsValue> {
+    SeedAttestation::sign(&key, id, seed, width, height).map_err(err_str)
+}
+
+/// See [sign_seed] for why this needs the `wasm-signing` feature
+#[cfg(feature = "wasm-signing")]
+#[wasm_bindgen]
+pub fn sign_submission(
+    key: unified::attestation::SigningKey,
+    id: &str,
+    data: &str,
+) -> Result<SubmissionAttestation, JsValue> {
+    let recording: SeededRecording = data.parse().map_err(err_str)?;
+    SubmissionAttestation::sign(&key, id, &recording).map_err(err_str)
+}
+
+#[wasm_bindgen]
+pub fn verify_attestation(
+    data: &str,
+    seed: SeedAttestation,
+    submission: SubmissionAttestation,
+    key: VerifyingKey,
+) -> Result<(), JsValue> {
+    unified::verify_attestation(data, &seed, &submission, &key).map_err(err_str)
+}
+
+/// `issued` must come from the issuer's own store, looked up by the id of the game, never from the submission
+#[wasm_bindgen]
+pub fn verify_commitment(
+    data: &str,
+    issued: IssuedSeed,
+    submitted_at: u64,
+    max_age: u64,
+) -> Result<(), JsValue> {
+    unified::verify_commitment(
+        data,
+        &issued.commitment,
+        &issued.opening,
+        submitted_at,
+        max_age,
+    )
+    .map_err(err_str)
+}
+
+/// Check the timings of a recording, using the default policy if none is given
+#[wasm_bindgen]
+pub fn check_timings(
+    recording: SeededRecording,
+    policy: Option<TimingPolicy>,
+) -> Result<(), JsValue> {
+    policy
+        .unwrap_or_default()
+        .check(&recording)
+        .map_err(err_str)
+}
+
+/// Look for signs of the game being played by a bot, see [ai::analysis]
+#[wasm_bindgen]
+pub fn analyze(data: &str) -> Result<ai::analysis::Analysis, JsValue> {
+    let parsed = unified::parse(data).map_err(err_str)?;
+    let reconstruction = unified::reconstruct(data).map_err(err_str)?;
+    Ok(ai::analysis::Analyzer::default().analyze(&parsed.moves(), &reconstruction))
+}
+
+/// The deepest search [hint] allows, as every level multiplies the time it takes
+pub const MAX_HINT_DEPTH: usize = 4;
+
+/// Throws if `depth` is above [MAX_HINT_DEPTH]
+#[wasm_bindgen]
+pub fn hint(board: Board, depth: usize) -> Result<ai::Hint, JsValue> {
+    if depth > MAX_HINT_DEPTH {
+        return Err(err_str(format!(
+            "depth {depth} is above the maximum of {MAX_HINT_DEPTH}"
+        )));
+    }
+    ai::Expectimax {
+        depth,
+        ..Default::default()
+    }
+    .hint(&board)
+    .map_err(err_str)
+}
+
 #[wasm_bindgen]
 pub fn lcg_sane(seed: u32) -> u32 {
     let mut seed = seed;